@@ -6,7 +6,7 @@ This is the ONLY remaining Python in the enclave — it exists because
 Node.js cannot perform the /dev/nsm ioctl natively (requires ctypes + fcntl).
 
 Usage:
-  python3 nsm-helper.py --user-data <hex> [--public-key <hex>]
+  python3 nsm-helper.py --user-data <hex> [--public-key <hex>] [--nonce <hex>]
 
 Output:
   Base64-encoded COSE_Sign1 attestation document on stdout.
@@ -23,7 +23,7 @@ NSM_DEVICE = "/dev/nsm"
 NSM_IOCTL_CMD = 0xC0200A00  # _IOWR(0x0A, 0, 32)
 NSM_RESPONSE_BUF_SIZE = 16384
 
-def get_attestation(user_data: bytes, public_key: bytes | None) -> bytes:
+def get_attestation(user_data: bytes, public_key: bytes | None, nonce: bytes | None) -> bytes:
     import cbor2
     import ctypes
     import fcntl
@@ -31,7 +31,7 @@ def get_attestation(user_data: bytes, public_key: bytes | None) -> bytes:
     request_payload = {
         "Attestation": {
             "user_data": user_data if user_data else None,
-            "nonce": None,
+            "nonce": nonce,
             "public_key": public_key,
         }
     }
@@ -70,12 +70,14 @@ def main() -> None:
     parser = argparse.ArgumentParser(description="NSM attestation helper")
     parser.add_argument("--user-data", required=True, help="Hex-encoded user data")
     parser.add_argument("--public-key", default="", help="Hex-encoded public key (optional)")
+    parser.add_argument("--nonce", default="", help="Hex-encoded nonce (optional)")
     args = parser.parse_args()
 
     user_data = bytes.fromhex(args.user_data) if args.user_data else b""
     public_key = bytes.fromhex(args.public_key) if args.public_key else None
+    nonce = bytes.fromhex(args.nonce) if args.nonce else None
 
-    doc = get_attestation(user_data, public_key)
+    doc = get_attestation(user_data, public_key, nonce)
     sys.stdout.write(base64.b64encode(doc).decode("ascii"))
 
 if __name__ == "__main__":
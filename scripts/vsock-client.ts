@@ -0,0 +1,103 @@
+#!/usr/bin/env npx tsx
+/**
+ * Send requests to the enclave vsock server and print the responses.
+ *
+ * Usage:
+ *   npx tsx scripts/vsock-client.ts health
+ *   npx tsx scripts/vsock-client.ts prove --circuit coinbase_attestation --inputs inputs.json [--metadata '{"timeoutMs":60000}']
+ *   npx tsx scripts/vsock-client.ts attestation --proof-hash 0xabc... [--nonce 0x...]
+ *   npx tsx scripts/vsock-client.ts getPublicKey
+ *   npx tsx scripts/vsock-client.ts listCircuits
+ *   npx tsx scripts/vsock-client.ts verify --circuit coinbase_attestation --proof 0x... [--public-inputs 0x...] [--disable-zk]
+ *   npx tsx scripts/vsock-client.ts errorDetail --error-id <errorId from an error response>
+ *   npx tsx scripts/vsock-client.ts --file request.json
+ *   npx tsx scripts/vsock-client.ts --framed --file requests.json
+ *
+ * Options:
+ *   --host <host>   Bridge host (default 127.0.0.1)
+ *   --port <port>   Bridge port (default ENCLAVE_BRIDGE_PORT or 15000)
+ *   --file <path>   Send the request object in the file as written (an array of
+ *                   requests with --framed); no flag-based checks are applied
+ *   --framed        Use line framing: one request per line on one connection,
+ *                   one response line each, instead of half-close framing
+ *
+ * Exit code: 0 on success, 1 when the enclave returned an error response (any
+ * of them, with --framed), 2 on usage or connection errors.
+ */
+
+import { readFileSync } from 'node:fs';
+import { parseArgs } from 'node:util';
+import {
+  buildVsockRequest,
+  parseVsockRequestFile,
+  sendVsockRequest,
+  sendVsockRequests,
+  exitCodeForResponse,
+} from '../src/aws/vsockClient.js';
+import type { VsockRequest } from '../src/tee/types.js';
+
+async function main(): Promise<number> {
+  const { values, positionals } = parseArgs({
+    allowPositionals: true,
+    options: {
+      host: { type: 'string', default: '127.0.0.1' },
+      port: { type: 'string', default: process.env.ENCLAVE_BRIDGE_PORT || '15000' },
+      file: { type: 'string' },
+      framed: { type: 'boolean' },
+      circuit: { type: 'string' },
+      inputs: { type: 'string' },
+      'proof-hash': { type: 'string' },
+      nonce: { type: 'string' },
+      proof: { type: 'string' },
+      'public-inputs': { type: 'string', multiple: true },
+      'disable-zk': { type: 'boolean' },
+      'error-id': { type: 'string' },
+      metadata: { type: 'string' },
+      'request-id': { type: 'string' },
+    },
+  });
+
+  let requests: VsockRequest[];
+  if (values.file) {
+    requests = parseVsockRequestFile(readFileSync(values.file, 'utf-8'));
+  } else {
+    requests = [buildVsockRequest({
+      type: positionals[0] || 'health',
+      requestId: values['request-id'],
+      circuitId: values.circuit,
+      inputs: values.inputs ? JSON.parse(readFileSync(values.inputs, 'utf-8')) : undefined,
+      proofHash: values['proof-hash'],
+      nonce: values.nonce,
+      proof: values.proof,
+      publicInputs: values['public-inputs'],
+      disableZk: values['disable-zk'],
+      errorId: values['error-id'],
+      metadata: values.metadata ? JSON.parse(values.metadata) : undefined,
+    })];
+  }
+
+  const host = values.host as string;
+  const port = parseInt(values.port as string, 10);
+  if (values.framed) {
+    const responses = await sendVsockRequests(host, port, requests);
+    for (const response of responses) {
+      console.log(JSON.stringify(response, null, 2));
+    }
+    return Math.max(...responses.map(exitCodeForResponse));
+  }
+
+  if (requests.length !== 1) {
+    throw new Error('A file with several requests needs --framed');
+  }
+  const response = await sendVsockRequest(host, port, requests[0]);
+  console.log(JSON.stringify(response, null, 2));
+  return exitCodeForResponse(response);
+}
+
+main().then(
+  (code) => process.exit(code),
+  (err) => {
+    console.error(`Error: ${err instanceof Error ? err.message : err}`);
+    process.exit(2);
+  },
+);
@@ -0,0 +1,51 @@
+/**
+ * Handler for the vsock `attestation` request, whose document binds the
+ * enclave's Ed25519 signing key as public_key.
+ */
+
+import { errorResponse, validateAttestationRequest } from './enclaveProtocol.js';
+import type { VsockRequest, VsockResponse } from './enclaveConnection.js';
+import type { EnclaveSigningKey } from './enclaveKeys.js';
+
+export interface AttestationDeps {
+  /** Request a document from NSM; null when the helper failed or NSM is absent */
+  attest: (userData: Buffer, publicKey?: Buffer, nonce?: Buffer) => Promise<Buffer | null>;
+  nsmAvailable: () => boolean;
+  signingKey: Pick<EnclaveSigningKey, 'publicKeyRaw'> | null;
+}
+
+/**
+ * Attest to proofHash (user_data) with an optional hex nonce. The nonce is
+ * echoed when given; publicKey is echoed whenever the signing key exists, with
+ * or without a nonce, so verifiers can cross-check the document.
+ */
+export async function handleAttestation(request: VsockRequest, deps: AttestationDeps): Promise<VsockResponse> {
+  const requestId = request.requestId || '';
+  const fields = validateAttestationRequest(request);
+  if (!fields.ok) {
+    return errorResponse(requestId, 'BAD_REQUEST', fields.error);
+  }
+
+  try {
+    const nonce = fields.nonce ? Buffer.from(fields.nonce, 'hex') : undefined;
+    const doc = await deps.attest(fields.userData, deps.signingKey?.publicKeyRaw, nonce);
+    if (doc) {
+      const response: VsockResponse = {
+        type: 'attestation',
+        requestId,
+        attestationDocument: doc.toString('base64'),
+      };
+      if (fields.nonce) response.nonce = fields.nonce;
+      if (deps.signingKey) {
+        response.publicKey = deps.signingKey.publicKeyRaw.toString('hex');
+      }
+      return response;
+    }
+    if (deps.nsmAvailable()) {
+      return errorResponse(requestId, 'ATTESTATION_FAILED', 'NSM attestation failed');
+    }
+    return errorResponse(requestId, 'ATTESTATION_UNAVAILABLE', 'NSM device not available');
+  } catch (err: any) {
+    return errorResponse(requestId, 'INTERNAL', err.message);
+  }
+}
@@ -0,0 +1,162 @@
+/**
+ * The filesystem side of one prove: a fresh work dir under the work base, the
+ * witness written into it, `bb prove` (and optionally `bb verify`) writing
+ * their output there, and the output read back before the dir is removed. The
+ * circuit artifacts are only read. bb is passed in, so the layout can be
+ * tested with a stand-in for the binary and without the enclave server.
+ */
+
+import * as fs from 'node:fs';
+import * as fsp from 'node:fs/promises';
+import * as path from 'node:path';
+import { CommandError } from './enclaveCommand.js';
+import type { CommandResult, RunCommandOptions } from './enclaveCommand.js';
+import { EnclaveError } from './enclaveProtocol.js';
+import type { ProveSettings } from './enclaveProtocol.js';
+import type { Logger } from './enclaveConnection.js';
+import { createWorkDir } from './workDir.js';
+
+export interface BbProveRequest {
+  requestId: string;
+  /** Compiled circuit JSON */
+  bytecode: string;
+  vk: string;
+  witness: Uint8Array;
+  settings: ProveSettings;
+  timeoutMs: number;
+  /** Run bb verify on the output before returning it */
+  verify: boolean;
+  /** Check the output sizes; a message fails the prove with ARTIFACT_INVALID */
+  checkArtifacts: (proofBytes: number, publicInputsBytes: number) => string | null;
+}
+
+export interface BbProveDeps {
+  workBase: string;
+  /** runCommand with the bb binary; label is 'bb prove' or 'bb verify' */
+  runBb: (label: string, args: string[], options: RunCommandOptions) => Promise<CommandResult>;
+  stallWindowMs: number;
+  verifyTimeoutMs: number;
+  /** Leave the work dir in place when the output fails checkArtifacts */
+  keepFailedWorkDir: boolean;
+  env?: NodeJS.ProcessEnv;
+  onEvent?: RunCommandOptions['onEvent'];
+  log: Logger;
+}
+
+export interface BbProveResult {
+  proof: Buffer;
+  /** Contents of bb's public_inputs file, or null when it wrote none */
+  publicInputs: Buffer | null;
+  bbMs: number;
+  verifyMs: number;
+}
+
+export async function runBbProve(request: BbProveRequest, deps: BbProveDeps): Promise<BbProveResult> {
+  const { requestId, settings } = request;
+  const { log } = deps;
+  const workdir = await createWorkDir(deps.workBase, requestId);
+  const proofDir = path.join(workdir, 'proof');
+  let keepWorkdir = false;
+
+  try {
+    await fsp.mkdir(proofDir, { recursive: true });
+    const witnessPath = path.join(workdir, 'witness.gz');
+    await fsp.writeFile(witnessPath, request.witness);
+
+    const zkFlags = settings.disableZk ? ['--disable_zk'] : [];
+    const bbCmd = [
+      'prove',
+      '-b', request.bytecode,
+      '-w', witnessPath,
+      '-o', proofDir,
+      '-k', request.vk,
+      '--oracle_hash', 'keccak',
+      ...zkFlags,
+      ...(settings.lowMemory ? ['--slow_low_memory'] : []),
+    ];
+    log('info', 'bb prove started', { action: 'enclave.bb.started', requestId, ...settings });
+
+    let bbMs: number;
+    try {
+      const run = await deps.runBb('bb prove', bbCmd, {
+        timeoutMs: request.timeoutMs,
+        stallWindowMs: deps.stallWindowMs,
+        env: deps.env,
+        workDir: workdir,
+        onEvent: deps.onEvent,
+      });
+      bbMs = run.durationMs;
+      log('info', 'bb prove succeeded', { action: 'enclave.bb.succeeded', requestId });
+    } catch (err: any) {
+      log('error', 'bb prove failed', {
+        action: 'enclave.bb.failed', requestId,
+        stdout: err.result?.stdout, stderr: err.result?.stderr,
+      });
+      throw err;
+    }
+
+    const proofFile = path.join(proofDir, 'proof');
+    if (!fs.existsSync(proofFile)) {
+      throw new EnclaveError('PROVE_FAILED', `bb prove did not produce output at ${proofFile}`);
+    }
+    const proof = await fsp.readFile(proofFile);
+    log('info', 'Proof read', { action: 'enclave.proof.read', requestId, proofBytes: proof.length });
+
+    const publicInputsPath = path.join(proofDir, 'public_inputs');
+    let publicInputs: Buffer | null = null;
+    if (fs.existsSync(publicInputsPath)) {
+      publicInputs = await fsp.readFile(publicInputsPath);
+      log('info', 'Public inputs read', { action: 'enclave.inputs.read', requestId, bytes: publicInputs.length });
+    } else {
+      log('info', 'No public_inputs file — returning empty publicInputs array', { action: 'enclave.inputs.empty', requestId });
+    }
+
+    // Reject truncated or padded artifacts before they leave the enclave
+    const artifactError = request.checkArtifacts(proof.length, publicInputs?.length ?? 0);
+    if (artifactError) {
+      keepWorkdir = deps.keepFailedWorkDir;
+      throw new EnclaveError('ARTIFACT_INVALID', `bb prove output failed validation: ${artifactError}`);
+    }
+
+    let verifyMs = 0;
+    if (request.verify) {
+      const verifyCmd = [
+        'verify',
+        '-p', proofFile,
+        '-i', publicInputsPath,
+        '-k', request.vk,
+        '--oracle_hash', 'keccak',
+        ...zkFlags,
+      ];
+      try {
+        const run = await deps.runBb('bb verify', verifyCmd, {
+          timeoutMs: deps.verifyTimeoutMs,
+          env: deps.env,
+          workDir: workdir,
+          onEvent: deps.onEvent,
+        });
+        verifyMs = run.durationMs;
+        log('info', 'bb verify succeeded', { action: 'enclave.bb.verified', requestId });
+      } catch (err: any) {
+        log('error', 'bb verify failed', {
+          action: 'enclave.bb.verify_failed', requestId, stderr: err.result?.stderr,
+        });
+        if (err instanceof CommandError && !err.result.timedOut) {
+          throw new EnclaveError(
+            'VERIFY_FAILED',
+            `Proof failed self-verification (exit ${err.result.exitCode}): ${err.result.stderr}`,
+          );
+        }
+        throw err;
+      }
+    }
+
+    return { proof, publicInputs, bbMs, verifyMs };
+  } finally {
+    if (keepWorkdir) {
+      log('error', 'Keeping work dir of failed proof', { action: 'enclave.workdir.kept', requestId, path: workdir });
+    } else {
+      await fsp.rm(workdir, { recursive: true, force: true }).catch(() => {});
+    }
+  }
+}
@@ -0,0 +1,68 @@
+/**
+ * Byte accounting for the enclave vsock transport.
+ *
+ * Totals are plain numbers that saturate at Number.MAX_SAFE_INTEGER instead of
+ * losing precision, so a long-running enclave never reports a wrapped or
+ * rounded counter. KeyedByteCounter splits the totals by request type.
+ */
+
+/** Add a byte count to a running total, saturating at Number.MAX_SAFE_INTEGER */
+export function saturatingAdd(total: number, bytes: number): number {
+  if (!Number.isFinite(bytes) || bytes <= 0) return total;
+  return Math.min(total + bytes, Number.MAX_SAFE_INTEGER);
+}
+
+/** Bytes received and sent over one stream (or aggregated across many) */
+export class ByteCounter {
+  received = 0;
+  sent = 0;
+
+  countReceived(bytes: number): void {
+    this.received = saturatingAdd(this.received, bytes);
+  }
+
+  countSent(bytes: number): void {
+    this.sent = saturatingAdd(this.sent, bytes);
+  }
+
+  /** Fold another counter's totals into this one */
+  add(other: ByteCounter): void {
+    this.countReceived(other.received);
+    this.countSent(other.sent);
+  }
+}
+
+/** Distinct keys a KeyedByteCounter tracks before folding new ones into OTHER_KEY */
+export const MAX_COUNTER_KEYS = 32;
+export const OTHER_KEY = 'other';
+
+/**
+ * ByteCounters keyed by a label such as the request type. Labels come from
+ * peers, so at most maxKeys are tracked; later labels share OTHER_KEY.
+ */
+export class KeyedByteCounter {
+  private readonly counters = new Map<string, ByteCounter>();
+
+  constructor(private readonly maxKeys = MAX_COUNTER_KEYS) {}
+
+  /** The counter for key, created on first use */
+  get(key: string): ByteCounter {
+    let counter = this.counters.get(key);
+    if (counter) return counter;
+
+    const tracked = this.counters.size - (this.counters.has(OTHER_KEY) ? 1 : 0);
+    if (key !== OTHER_KEY && tracked >= this.maxKeys) {
+      return this.get(OTHER_KEY);
+    }
+    counter = new ByteCounter();
+    this.counters.set(key, counter);
+    return counter;
+  }
+
+  /** Totals per key, for the health response */
+  toJSON(): Record<string, { received: number; sent: number }> {
+    return Object.fromEntries(
+      [...this.counters].map(([key, counter]) => [key, { received: counter.received, sent: counter.sent }]),
+    );
+  }
+}
@@ -0,0 +1,159 @@
+/**
+ * Circuit discovery for the enclave: every subdirectory of the circuits base
+ * dir that contains a compiled `target/<circuit_id>.json` and a `target/vk/vk`
+ * is served, so adding a circuit to the image needs no code change.
+ */
+
+import * as fsp from 'node:fs/promises';
+import * as path from 'node:path';
+import { readCircuitJson } from '../circuit/artifactGuard.js';
+
+/** Directory and artifact filenames of one circuit, relative to the base dir */
+export interface CircuitEntry {
+  dir: string;
+  bytecode: string;
+  vk: string;
+}
+
+/** One circuit directory as reported to the parent by a listCircuits request */
+export interface CircuitListing {
+  circuitId: string;
+  dir: string;
+  status: 'complete' | 'incomplete';
+  /** Expected files that are absent, relative to the circuit directory */
+  missing: string[];
+}
+
+export interface DiscoveryResult {
+  circuits: Record<string, CircuitEntry>;
+  /** Directories that look like circuits but cannot be served */
+  skipped: Array<{ dir: string; reason: string }>;
+  /** Noir library packages; expected next to the circuits and never served */
+  libraries: string[];
+}
+
+const CIRCUIT_ID_PATTERN = /^[A-Za-z0-9_]+$/;
+const VK_PATH = 'vk/vk';
+
+async function exists(filePath: string): Promise<boolean> {
+  return fsp.access(filePath).then(() => true, () => false);
+}
+
+/** Pick the compiled JSON in target/: the only one, or the one named after the directory */
+function pickCircuitJson(dir: string, jsonFiles: string[]): string | null {
+  if (jsonFiles.length === 1) return jsonFiles[0];
+  const expected = `${dir.replace(/-/g, '_')}.json`;
+  return jsonFiles.includes(expected) ? expected : null;
+}
+
+/** Circuit directories under baseDir; hidden dirs and per-request work dirs (`proof-*`) are not circuits */
+async function circuitDirs(baseDir: string): Promise<string[]> {
+  const entries = await fsp.readdir(baseDir, { withFileTypes: true });
+  return entries
+    .filter((entry) => entry.isDirectory() && !entry.name.startsWith('.') && !entry.name.startsWith('proof-'))
+    .map((entry) => entry.name)
+    .sort();
+}
+
+/**
+ * Whether a directory is a Noir library package (`type = "lib"` in Nargo.toml).
+ * The image ships the circuits' library dependencies (coinbase-libs,
+ * keccak256) alongside them; those never have artifacts of their own.
+ */
+async function isNoirLibrary(baseDir: string, dir: string): Promise<boolean> {
+  const manifest = await fsp.readFile(path.join(baseDir, dir, 'Nargo.toml'), 'utf-8').catch(() => '');
+  return /^\s*type\s*=\s*["']lib["']\s*(#.*)?$/m.test(manifest);
+}
+
+async function targetJsonFiles(baseDir: string, dir: string): Promise<string[]> {
+  const files = await fsp.readdir(path.join(baseDir, dir, 'target')).catch(() => [] as string[]);
+  return files.filter((file) => file.endsWith('.json')).sort();
+}
+
+/**
+ * Scan baseDir for circuits. The canonical circuit ID is the JSON filename.
+ * Directories without a usable target JSON and vk are reported in `skipped`,
+ * Noir library packages in `libraries`; hidden directories and per-request
+ * work dirs (`proof-*`) are ignored.
+ */
+export async function discoverCircuits(baseDir: string, maxArtifactBytes: number): Promise<DiscoveryResult> {
+  const result: DiscoveryResult = { circuits: {}, skipped: [], libraries: [] };
+
+  let dirs: string[];
+  try {
+    dirs = await circuitDirs(baseDir);
+  } catch (err: any) {
+    result.skipped.push({ dir: baseDir, reason: `cannot read circuits directory: ${err.code || err.message}` });
+    return result;
+  }
+
+  for (const dir of dirs) {
+    if (await isNoirLibrary(baseDir, dir)) {
+      result.libraries.push(dir);
+      continue;
+    }
+    const targetDir = path.join(baseDir, dir, 'target');
+    const jsonFiles = await targetJsonFiles(baseDir, dir);
+
+    if (jsonFiles.length === 0) {
+      result.skipped.push({ dir, reason: 'no target/<circuit>.json' });
+      continue;
+    }
+    const bytecode = pickCircuitJson(dir, jsonFiles);
+    if (!bytecode) {
+      result.skipped.push({ dir, reason: `ambiguous target JSON: ${jsonFiles.join(', ')}` });
+      continue;
+    }
+
+    const circuitId = bytecode.slice(0, -'.json'.length);
+    if (!CIRCUIT_ID_PATTERN.test(circuitId)) {
+      result.skipped.push({ dir, reason: `invalid circuit id '${circuitId}'` });
+      continue;
+    }
+    if (result.circuits[circuitId]) {
+      result.skipped.push({ dir, reason: `duplicate circuit id '${circuitId}' (already in ${result.circuits[circuitId].dir})` });
+      continue;
+    }
+    if (!(await exists(path.join(targetDir, VK_PATH)))) {
+      result.skipped.push({ dir, reason: `missing target/${VK_PATH}` });
+      continue;
+    }
+
+    try {
+      await readCircuitJson(path.join(targetDir, bytecode), maxArtifactBytes);
+    } catch (err: any) {
+      result.skipped.push({ dir, reason: err.message });
+      continue;
+    }
+
+    result.circuits[circuitId] = { dir, bytecode, vk: VK_PATH };
+  }
+
+  return result;
+}
+
+/**
+ * List every circuit directory with the artifacts it is missing, so broken
+ * directories show up as incomplete instead of disappearing. Only checks that
+ * files exist; discoverCircuits decides what is actually served. Noir library
+ * packages are not circuits and are left out.
+ *
+ * @throws Error if baseDir cannot be read
+ */
+export async function listCircuits(baseDir: string): Promise<CircuitListing[]> {
+  const listings: CircuitListing[] = [];
+  for (const dir of await circuitDirs(baseDir)) {
+    if (await isNoirLibrary(baseDir, dir)) continue;
+    const bytecode = pickCircuitJson(dir, await targetJsonFiles(baseDir, dir));
+    const circuitId = bytecode ? bytecode.slice(0, -'.json'.length) : dir.replace(/-/g, '_');
+
+    const expected = [`target/${circuitId}.json`, `target/${VK_PATH}`, 'Nargo.toml'];
+    const missing: string[] = [];
+    for (const file of expected) {
+      if (!(await exists(path.join(baseDir, dir, file)))) missing.push(file);
+    }
+
+    listings.push({ circuitId, dir, status: missing.length === 0 ? 'complete' : 'incomplete', missing });
+  }
+  return listings;
+}
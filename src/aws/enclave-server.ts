@@ -12,9 +12,34 @@
  *
  * Supported request types:
  *   health       → { type: "health", requestId }
- *   prove        → { type: "prove", circuitId, inputs, requestId, encryptedPayload? }
- *   attestation  → { type: "attestation", requestId, proofHash?, metadata? }
+ *   prove        → { type: "prove", circuitId, inputs, requestId, encryptedPayload?, metadata? }
+ *   attestation  → { type: "attestation", requestId, proofHash?, nonce? } → { attestationDocument, nonce?, publicKey }
  *   getPublicKey → { type: "getPublicKey", requestId }
+ *   verify       → { type: "verify", circuitId, proof, publicInputs, requestId } → { isValid }
+ *   listCircuits → { type: "listCircuits", requestId } → { circuits: [{ circuitId, status, missing }] }
+ *
+ * Prove metadata is validated against a fixed schema (see enclaveProtocol.ts);
+ * unknown keys are rejected unless VSOCK_LENIENT_METADATA=true. disableZk is
+ * refused unless ALLOW_DISABLE_ZK=true, and always for encrypted requests.
+ *
+ * A connection carries either one request terminated by half-close, or, when
+ * its first line is a complete JSON object, any number of newline-delimited
+ * requests answered in order (one response line each). Per-connection handling
+ * lives in enclaveConnection.ts.
+ *
+ * Handler exceptions are answered with INTERNAL instead of crashing the
+ * process, and the listener is re-created with backoff after a server error.
+ * Both are counted in the health response (handlerPanics, serverRestarts),
+ * along with the total request and response bytes moved since startup
+ * (bytesReceived, bytesSent).
+ *
+ * SIGTERM/SIGINT close the listener and refuse new proofs (SHUTTING_DOWN, health
+ * status "draining"); in-flight requests get SHUTDOWN_GRACE_SECS (default 120)
+ * to finish before the process exits.
+ *
+ * Error responses carry an errorId. Unless DETAIL_LEVEL=full, messages that may
+ * contain paths or toolchain output are replaced by a generic one; the full
+ * message is logged under the errorId (action enclave.error.detail).
  *
  * Entry point: node enclave-server.bundle.js (esbuild bundled)
  */
@@ -32,6 +57,40 @@ import { Noir } from '@noir-lang/noir_js';
 import { formatCoinbaseInputs, formatOidcInputs } from '../prover/inputFormatter.js';
 import type { OidcCircuitInputs } from '../prover/inputFormatter.js';
 import type { CircuitParams } from '../input/inputBuilder.js';
+import {
+  validateProveMetadata,
+  validateVerifyRequest,
+  effectiveProveSettings,
+  errorResponse,
+  EnclaveError,
+  getErrorDetailLevel,
+} from './enclaveProtocol.js';
+import type { ProveMetadata, ProveSettings } from './enclaveProtocol.js';
+import { runCommand, CommandError, exitedNormally, getStallWindowMs } from './enclaveCommand.js';
+import { readCircuitJson, getMaxArtifactBytes } from '../circuit/artifactGuard.js';
+import type { CommandEvent } from './enclaveCommand.js';
+import { ConnectionPool, createServerStats, getVsockWorkers } from './enclaveConnection.js';
+import type { VsockRequest, VsockResponse } from './enclaveConnection.js';
+import {
+  getStateDir,
+  defaultWorkDirBase,
+  getWorkDirBase,
+  checkWritable,
+  prepareWorkDirBase,
+  getWorkDirStatus,
+  createWorkDir,
+} from './workDir.js';
+import { getJsonLimits } from './jsonGuard.js';
+import { countPublicInputFields, getProofSizeBounds, validateProofArtifacts } from './proofArtifacts.js';
+import { discoverCircuits, listCircuits } from './circuitDiscovery.js';
+import type { CircuitEntry } from './circuitDiscovery.js';
+import { generateEnclaveKeyPair, generateSigningKey, openEnvelope } from './enclaveKeys.js';
+import { handleAttestation } from './attestation.js';
+import { runBbProve } from './bbProve.js';
+import { ErrorLog } from './errorLog.js';
+import { ProveLimiter, getProveConcurrency, getPriorityAgingMs } from './proveLimiter.js';
+import { ShutdownController, getShutdownGraceMs } from '../shutdown.js';
+import type { EnclaveKeyPair, EnclaveSigningKey } from './enclaveKeys.js';
 
 const execFileAsync = promisify(execFile);
 
@@ -45,9 +104,16 @@ const AF_VSOCK = 40;
 const VMADDR_CID_ANY = 0xFFFFFFFF;
 
 const CIRCUIT_BASE_DIR = '/app/circuits';
+/** All enclave writes go here, never under CIRCUIT_BASE_DIR (which may be read-only) */
+const STATE_DIR = getStateDir(CIRCUIT_BASE_DIR);
+const WORK_DIR_BASE = getWorkDirBase(defaultWorkDirBase(STATE_DIR));
 
-/** Canonical circuit ID → directory and artifact filenames */
-const CIRCUITS: Record<string, { dir: string; bytecode: string; vk: string }> = {
+/**
+ * Circuits shipped with the image. They stay registered even when discovery
+ * misses them, so a broken artifact surfaces as CIRCUIT_UNAVAILABLE rather
+ * than CIRCUIT_NOT_FOUND.
+ */
+const KNOWN_CIRCUITS: Record<string, CircuitEntry> = {
   coinbase_attestation: {
     dir: 'coinbase-attestation',
     bytecode: 'coinbase_attestation.json',
@@ -65,13 +131,42 @@ const CIRCUITS: Record<string, { dir: string; bytecode: string; vk: string }> =
   },
 };
 
+/** Canonical circuit ID → directory and artifact filenames; discovered at startup */
+let CIRCUITS: Record<string, CircuitEntry> = { ...KNOWN_CIRCUITS };
+
 const PROVE_TIMEOUT_MS = 120_000;
+const VERIFY_TIMEOUT_MS = 30_000;
+/** bb prove is killed if neither its CPU time nor its output advances for this long */
+const PROVE_STALL_WINDOW_MS = getStallWindowMs();
+const VSOCK_LENIENT_METADATA = process.env.VSOCK_LENIENT_METADATA === 'true';
+/** Non-ZK proofs (metadata.disableZk) are for internal testing; never honoured unless set */
+const ALLOW_DISABLE_ZK = process.env.ALLOW_DISABLE_ZK === 'true';
+const MAX_ARTIFACT_BYTES = getMaxArtifactBytes();
+const PROOF_SIZE_BOUNDS = getProofSizeBounds();
+/** Leave the work dir in place when proof artifacts fail validation, for post-mortem */
+const KEEP_FAILED_WORKDIR = process.env.KEEP_FAILED_WORKDIR === 'true';
 const NSM_DEVICE = '/dev/nsm';
+const MAX_SERVER_RESTARTS = 5;
+const ERROR_ID_PATTERN = /^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$/;
+const SERVER_RESTART_BACKOFF_MS = 1_000;
+
+const serverStats = createServerStats();
+/**
+ * Only this many proofs run at once (PROVE_CONCURRENCY); further proves queue
+ * by priority, aged by PROVE_PRIORITY_AGING_MS. Non-prove requests bypass it.
+ */
+const proveLimiter = new ProveLimiter(getProveConcurrency(), getPriorityAgingMs());
+/** Only this many connections are served at once (VSOCK_WORKERS); the rest wait for a worker */
+const connectionPool = new ConnectionPool(getVsockWorkers());
+/** Full detail of recent error responses, looked up by errorId (errorDetail requests) */
+const errorLog = new ErrorLog();
+const shutdown = new ShutdownController();
+const SHUTDOWN_GRACE_MS = getShutdownGraceMs();
 
 // E2E encryption key pair (initialized at startup)
-let enclavePrivateKey: crypto.KeyObject;
-let enclavePublicKeyRaw: Buffer;
-let enclaveKeyId: string;
+let enclaveKeys: EnclaveKeyPair | null = null;
+// Ed25519 key bound into `attestation` documents (initialized at startup)
+let signingKey: EnclaveSigningKey | null = null;
 
 // ─────────────────────────────────────────────────────────────
 // Logging (stderr only — no files in enclave)
@@ -102,23 +197,19 @@ function logError(msg: string, extra: Record<string, unknown> = {}): void {
   log('error', msg, extra);
 }
 
-// ─────────────────────────────────────────────────────────────
-// X25519 SPKI helper
-// ─────────────────────────────────────────────────────────────
-
-const X25519_SPKI_HEADER = Buffer.from('302a300506032b656e032100', 'hex');
-
-function importX25519PublicKey(rawHex: string): crypto.KeyObject {
-  const rawBytes = Buffer.from(rawHex, 'hex');
-  const der = Buffer.concat([X25519_SPKI_HEADER, rawBytes]);
-  return crypto.createPublicKey({ key: der, format: 'der', type: 'spki' });
+/** Structured completion event for each external command (see enclaveCommand.ts) */
+function logCommandEvent(requestId: string): (event: CommandEvent) => void {
+  return (event) => {
+    const level = event.exitCode === 0 ? 'info' : 'error';
+    log(level, 'Command completed', { action: 'enclave.command.completed', requestId, ...event });
+  };
 }
 
 // ─────────────────────────────────────────────────────────────
 // NSM attestation (via Python helper for /dev/nsm ioctl)
 // ─────────────────────────────────────────────────────────────
 
-async function getNsmAttestation(userData?: Buffer, publicKey?: Buffer): Promise<Buffer | null> {
+async function getNsmAttestation(userData?: Buffer, publicKey?: Buffer, nonce?: Buffer): Promise<Buffer | null> {
   if (!fs.existsSync(NSM_DEVICE)) {
     logInfo('NSM device not available — skipping attestation', { action: 'enclave.nsm.skipped' });
     return null;
@@ -130,6 +221,9 @@ async function getNsmAttestation(userData?: Buffer, publicKey?: Buffer): Promise
     if (publicKey && publicKey.length > 0) {
       args.push('--public-key', publicKey.toString('hex'));
     }
+    if (nonce && nonce.length > 0) {
+      args.push('--nonce', nonce.toString('hex'));
+    }
 
     const { stdout, stderr } = await execFileAsync('python3', [helperPath, ...args], {
       timeout: 10_000,
@@ -176,8 +270,9 @@ interface CircuitPaths {
 function getCircuitPaths(circuitId: string): CircuitPaths {
   const meta = CIRCUITS[circuitId];
   if (!meta) {
-    throw new Error(
-      `Unknown circuitId '${circuitId}'. Supported: ${Object.keys(CIRCUITS).join(', ')}`
+    throw new EnclaveError(
+      'CIRCUIT_NOT_FOUND',
+      `Unknown circuitId '${circuitId}'. Supported: ${Object.keys(CIRCUITS).join(', ')}`,
     );
   }
   const base = path.join(CIRCUIT_BASE_DIR, meta.dir);
@@ -196,9 +291,11 @@ interface ProofResult {
   proof: string;
   publicInputs: string[];
   attestationDocument?: string;
+  settings: ProveSettings;
   timing: {
     witnessMs: number;
     bbMs: number;
+    verifyMs: number;
     nsmMs: number;
     totalMs: number;
   };
@@ -268,11 +365,13 @@ async function generateProof(
   circuitId: string,
   inputs: Record<string, any>,
   requestId: string,
+  options: ProveMetadata = {},
 ): Promise<ProofResult> {
   const meta = CIRCUITS[circuitId];
   if (!meta) {
-    throw new Error(
-      `Unknown circuitId '${circuitId}'. Supported: ${Object.keys(CIRCUITS).join(', ')}`
+    throw new EnclaveError(
+      'CIRCUIT_NOT_FOUND',
+      `Unknown circuitId '${circuitId}'. Supported: ${Object.keys(CIRCUITS).join(', ')}`,
     );
   }
   const paths = getCircuitPaths(circuitId);
@@ -280,228 +379,170 @@ async function generateProof(
   // Verify circuit artifacts exist
   for (const [label, filePath] of [['bytecode', paths.bytecode], ['vk', paths.vk]] as const) {
     if (!fs.existsSync(filePath)) {
-      throw new Error(`Circuit artifact missing: ${label} at ${filePath}`);
+      throw new EnclaveError('CIRCUIT_UNAVAILABLE', `Circuit artifact missing: ${label} at ${filePath}`);
     }
   }
 
-  // Create temp working directory
-  const workdir = await fsp.mkdtemp(path.join(CIRCUIT_BASE_DIR, `proof-${requestId}-`));
-  const proofDir = path.join(workdir, 'proof');
-  await fsp.mkdir(proofDir, { recursive: true });
+  const tStart = Date.now();
+  logInfo('Proof generation started', {
+    action: 'enclave.prove.started', requestId, circuitId,
+    tenant: options.tenant, priority: options.priority,
+  });
 
+  // Step 1: Load compiled circuit JSON
+  const circuitJsonPath = path.join(CIRCUIT_BASE_DIR, meta.dir, 'target', meta.bytecode);
+  let circuitJson: Record<string, unknown>;
   try {
-    const tStart = Date.now();
-    logInfo('Proof generation started', { action: 'enclave.prove.started', requestId, circuitId });
-
-    // Step 1: Load compiled circuit JSON
-    const circuitJsonPath = path.join(CIRCUIT_BASE_DIR, meta.dir, 'target', meta.bytecode);
-    const circuitJson = JSON.parse(await fsp.readFile(circuitJsonPath, 'utf-8'));
-    logInfo('Circuit JSON loaded', { action: 'enclave.circuit.loaded', requestId, path: circuitJsonPath });
-
-    // Step 2: Format inputs for noir_js
-    let noirInputs: Record<string, unknown>;
-    if (circuitId === 'oidc_domain_attestation') {
-      // OIDC: inputs is OidcProvePayload { jwt, jwks, scope, provider } — validate + build circuit inputs
-      const { prepareOidcCircuitInputs } = await import('../prover/oidcProver.js');
-      const oidcInputs = prepareOidcCircuitInputs(inputs as any);
-      noirInputs = formatOidcInputs(oidcInputs);
-    } else {
-      const normalized = normalizeToCircuitParams(circuitId, inputs);
-      noirInputs = formatCoinbaseInputs(
-        circuitId as 'coinbase_attestation' | 'coinbase_country_attestation',
-        normalized as CircuitParams,
-      );
-    }
-
-    // Step 3: Execute circuit via noir_js to generate witness
-    const noir = new Noir(circuitJson);
-    let witnessData: Uint8Array;
-    try {
-      const { witness } = await noir.execute(noirInputs as any);
-      witnessData = witness;
-    } catch (error: any) {
-      throw new Error(`noir_js execute failed: ${error.message || error}`);
-    }
-
-    const tWitness = Date.now();
-    logInfo('noir_js witness generated', {
-      action: 'enclave.witness.generated', requestId, witnessBytes: witnessData.length,
-    });
-
-    // Step 4: Write witness to temp file
-    const witnessPath = path.join(workdir, 'witness.gz');
-    await fsp.writeFile(witnessPath, witnessData);
-
-    // Step 5: Run bb prove
-    const bbCmd = [
-      'prove',
-      '-b', paths.bytecode,
-      '-w', witnessPath,
-      '-o', proofDir,
-      '-k', paths.vk,
-      '--oracle_hash', 'keccak',
-    ];
-    logInfo('bb prove started', { action: 'enclave.bb.started', requestId, cmd: `bb ${bbCmd.join(' ')}` });
-
-    try {
-      await execFileAsync('bb', bbCmd, {
-        timeout: PROVE_TIMEOUT_MS,
-        env: { ...process.env, HOME: '/root' },
-      });
-      logInfo('bb prove succeeded', { action: 'enclave.bb.succeeded', requestId });
-    } catch (err: any) {
-      logError('bb prove failed', {
-        action: 'enclave.bb.failed', requestId,
-        returncode: err.code, stdout: err.stdout, stderr: err.stderr,
-      });
-      throw new Error(`bb prove failed (exit ${err.code}): ${err.stderr}`);
-    }
-
-    const tBb = Date.now();
-
-    // Step 6: Read proof bytes
-    const proofFile = path.join(proofDir, 'proof');
-    if (!fs.existsSync(proofFile)) {
-      throw new Error(`bb prove did not produce output at ${proofFile}`);
-    }
-
-    const proofBytes = await fsp.readFile(proofFile);
-    const proofHex = '0x' + proofBytes.toString('hex');
-    logInfo('Proof read', { action: 'enclave.proof.read', requestId, proofBytes: proofBytes.length });
-
-    // Step 7: Read public inputs
-    const publicInputs: string[] = [];
-    const publicInputsPath = path.join(proofDir, 'public_inputs');
-    if (fs.existsSync(publicInputsPath)) {
-      const piBytes = await fsp.readFile(publicInputsPath);
-      publicInputs.push('0x' + piBytes.toString('hex'));
-      logInfo('Public inputs read', { action: 'enclave.inputs.read', requestId, bytes: piBytes.length });
-    } else {
-      logInfo('No public_inputs file — returning empty publicInputs array', { action: 'enclave.inputs.empty', requestId });
-    }
-
-    // Step 8: NSM attestation
-    let attestationB64: string | undefined;
-    const proofHash = crypto.createHash('sha256').update(proofBytes).digest();
-    const nsmDoc = await getNsmAttestation(proofHash);
-    if (nsmDoc) {
-      attestationB64 = nsmDoc.toString('base64');
-      logInfo('NSM attestation obtained', { action: 'enclave.nsm.obtained', requestId, docBytes: nsmDoc.length });
-    } else if (fs.existsSync(NSM_DEVICE)) {
-      throw new Error('NSM device exists but attestation failed. Refusing to return proof without attestation.');
-    } else {
-      logInfo('NSM device not present — attestation skipped (non-enclave environment)', { action: 'enclave.nsm.skipped', requestId });
-    }
-
-    const tNsm = Date.now();
-
-    const result: ProofResult = {
-      proof: proofHex,
-      publicInputs,
-      timing: {
-        witnessMs: tWitness - tStart,
-        bbMs: tBb - tWitness,
-        nsmMs: tNsm - tBb,
-        totalMs: tNsm - tStart,
-      },
-    };
-    if (attestationB64) {
-      result.attestationDocument = attestationB64;
-    }
-
-    return result;
-  } finally {
-    // Clean up workdir
-    await fsp.rm(workdir, { recursive: true, force: true }).catch(() => {});
+    circuitJson = await readCircuitJson(circuitJsonPath, MAX_ARTIFACT_BYTES);
+  } catch (err: any) {
+    throw new EnclaveError('CIRCUIT_UNAVAILABLE', err.message);
+  }
+  logInfo('Circuit JSON loaded', { action: 'enclave.circuit.loaded', requestId, path: circuitJsonPath });
+
+  // Step 2: Format inputs for noir_js
+  let noirInputs: Record<string, unknown>;
+  if (circuitId === 'oidc_domain_attestation') {
+    // OIDC: inputs is OidcProvePayload { jwt, jwks, scope, provider } — validate + build circuit inputs
+    const { prepareOidcCircuitInputs } = await import('../prover/oidcProver.js');
+    const oidcInputs = prepareOidcCircuitInputs(inputs as any);
+    noirInputs = formatOidcInputs(oidcInputs);
+  } else if (!KNOWN_CIRCUITS[circuitId]) {
+    // Discovered circuits have no formatter here; inputs must already match the circuit ABI
+    noirInputs = inputs;
+  } else {
+    const normalized = normalizeToCircuitParams(circuitId, inputs);
+    noirInputs = formatCoinbaseInputs(
+      circuitId as 'coinbase_attestation' | 'coinbase_country_attestation',
+      normalized as CircuitParams,
+    );
   }
-}
 
-// ─────────────────────────────────────────────────────────────
-// Encryption / Decryption
-// ─────────────────────────────────────────────────────────────
+  // Step 3: Execute circuit via noir_js to generate witness
+  const noir = new Noir(circuitJson);
+  let witnessData: Uint8Array;
+  try {
+    const { witness } = await noir.execute(noirInputs as any);
+    witnessData = witness;
+  } catch (error: any) {
+    throw new EnclaveError('WITNESS_UNSATISFIED', `noir_js execute failed: ${error.message || error}`);
+  }
 
-function decryptPayload(encryptedPayload: {
-  ephemeralPublicKey: string;
-  iv: string;
-  ciphertext: string;
-  authTag: string;
-  keyId: string;
-}): string {
-  // Verify keyId matches
-  if (encryptedPayload.keyId !== enclaveKeyId) {
-    throw new Error(`Key ID mismatch: expected ${enclaveKeyId}, got ${encryptedPayload.keyId}`);
-  }
-
-  // Import ephemeral public key
-  const ephemeralPubKeyObj = importX25519PublicKey(encryptedPayload.ephemeralPublicKey);
-
-  // ECDH: derive shared secret
-  const sharedSecret = crypto.diffieHellman({
-    publicKey: ephemeralPubKeyObj,
-    privateKey: enclavePrivateKey,
+  const tWitness = Date.now();
+  logInfo('noir_js witness generated', {
+    action: 'enclave.witness.generated', requestId, witnessBytes: witnessData.length,
   });
 
-  // Derive AES key: SHA-256(shared_secret)
-  const aesKey = crypto.createHash('sha256').update(sharedSecret).digest();
-
-  // AES-256-GCM decrypt
-  const iv = Buffer.from(encryptedPayload.iv, 'hex');
-  const ciphertext = Buffer.from(encryptedPayload.ciphertext, 'hex');
-  const authTag = Buffer.from(encryptedPayload.authTag, 'hex');
+  // Steps 4-8: bb prove (and optional self-verification) in a fresh work dir under WORK_DIR_BASE
+  const settings = effectiveProveSettings(options);
+  const bb = await runBbProve({
+    requestId,
+    bytecode: paths.bytecode,
+    vk: paths.vk,
+    witness: witnessData,
+    settings,
+    timeoutMs: options.timeoutMs ?? PROVE_TIMEOUT_MS,
+    verify: options.verifyBeforeReturn ?? false,
+    checkArtifacts: (proofBytes, publicInputsBytes) => validateProofArtifacts(
+      proofBytes,
+      publicInputsBytes,
+      countPublicInputFields(circuitJson.abi),
+      PROOF_SIZE_BOUNDS,
+    ),
+  }, {
+    workBase: WORK_DIR_BASE,
+    runBb: (label, args, runOptions) => runCommand(label, 'bb', args, runOptions),
+    stallWindowMs: PROVE_STALL_WINDOW_MS,
+    verifyTimeoutMs: VERIFY_TIMEOUT_MS,
+    keepFailedWorkDir: KEEP_FAILED_WORKDIR,
+    env: { ...process.env, HOME: '/root' },
+    onEvent: logCommandEvent(requestId),
+    log,
+  });
+  const proofHex = '0x' + bb.proof.toString('hex');
+  const publicInputs = bb.publicInputs ? ['0x' + bb.publicInputs.toString('hex')] : [];
+
+  const tVerify = Date.now();
+
+  // Step 9: NSM attestation
+  let attestationB64: string | undefined;
+  const proofHash = crypto.createHash('sha256').update(bb.proof).digest();
+  const nonce = options.nonce ? Buffer.from(options.nonce, 'hex') : undefined;
+  const nsmDoc = options.skipAttestation ? null : await getNsmAttestation(proofHash, undefined, nonce);
+  if (options.skipAttestation) {
+    logInfo('Attestation skipped by request metadata', { action: 'enclave.nsm.skipped', requestId });
+  } else if (nsmDoc) {
+    attestationB64 = nsmDoc.toString('base64');
+    logInfo('NSM attestation obtained', { action: 'enclave.nsm.obtained', requestId, docBytes: nsmDoc.length });
+  } else if (fs.existsSync(NSM_DEVICE)) {
+    throw new EnclaveError(
+      'ATTESTATION_FAILED',
+      'NSM device exists but attestation failed. Refusing to return proof without attestation.',
+    );
+  } else {
+    logInfo('NSM device not present — attestation skipped (non-enclave environment)', { action: 'enclave.nsm.skipped', requestId });
+  }
 
-  const decipher = crypto.createDecipheriv('aes-256-gcm', aesKey, iv);
-  decipher.setAuthTag(authTag);
-  const decrypted = Buffer.concat([decipher.update(ciphertext), decipher.final()]);
+  const tNsm = Date.now();
+
+  const result: ProofResult = {
+    proof: proofHex,
+    publicInputs,
+    settings,
+    timing: {
+      witnessMs: tWitness - tStart,
+      bbMs: bb.bbMs,
+      verifyMs: bb.verifyMs,
+      nsmMs: tNsm - tVerify,
+      totalMs: tNsm - tStart,
+    },
+  };
+  if (attestationB64) {
+    result.attestationDocument = attestationB64;
+  }
 
-  return decrypted.toString('utf-8');
+  return result;
 }
 
 // ─────────────────────────────────────────────────────────────
 // Request handlers
 // ─────────────────────────────────────────────────────────────
 
-interface VsockRequest {
-  type: string;
-  circuitId?: string;
-  inputs?: Record<string, any>;
-  requestId?: string;
-  encryptedPayload?: {
-    ephemeralPublicKey: string;
-    iv: string;
-    ciphertext: string;
-    authTag: string;
-    keyId: string;
-  };
-  proofHash?: string;
-  metadata?: Record<string, unknown>;
-}
-
-interface VsockResponse {
-  type: string;
-  requestId: string;
-  [key: string]: unknown;
-}
-
-function handleHealth(request: VsockRequest): VsockResponse {
+async function handleHealth(request: VsockRequest): Promise<VsockResponse> {
   return {
     type: 'health',
     requestId: request.requestId || '',
-    status: 'ok',
+    status: shutdown.draining ? 'draining' : 'ok',
+    handlerPanics: serverStats.handlerPanics,
+    serverRestarts: serverStats.serverRestarts,
+    bytesReceived: serverStats.transfer.received,
+    bytesSent: serverStats.transfer.sent,
+    bytesByType: serverStats.transferByType.toJSON(),
+    workDir: await getWorkDirStatus(WORK_DIR_BASE),
+    proves: {
+      running: proveLimiter.running,
+      queued: proveLimiter.queued,
+      concurrency: proveLimiter.concurrency,
+    },
+    connections: {
+      active: connectionPool.busy,
+      queued: connectionPool.queued,
+      workers: connectionPool.workers,
+    },
   };
 }
 
 function handleGetPublicKey(request: VsockRequest): VsockResponse {
   const requestId = request.requestId || '';
 
-  if (!enclavePublicKeyRaw) {
-    return { type: 'error', requestId, error: 'Key pair not initialized' };
+  if (!enclaveKeys) {
+    return errorResponse(requestId, 'KEY_NOT_INITIALIZED', 'Key pair not initialized');
   }
 
   const response: VsockResponse = {
     type: 'publicKey',
     requestId,
-    publicKey: enclavePublicKeyRaw.toString('hex'),
-    keyId: enclaveKeyId,
+    publicKey: enclaveKeys.publicKeyRaw.toString('hex'),
+    keyId: enclaveKeys.keyId,
   };
 
   // Async attestation would block; do synchronous check
@@ -512,18 +553,18 @@ function handleGetPublicKey(request: VsockRequest): VsockResponse {
 async function handleGetPublicKeyAsync(request: VsockRequest): Promise<VsockResponse> {
   const requestId = request.requestId || '';
 
-  if (!enclavePublicKeyRaw) {
-    return { type: 'error', requestId, error: 'Key pair not initialized' };
+  if (!enclaveKeys) {
+    return errorResponse(requestId, 'KEY_NOT_INITIALIZED', 'Key pair not initialized');
   }
 
   const response: VsockResponse = {
     type: 'publicKey',
     requestId,
-    publicKey: enclavePublicKeyRaw.toString('hex'),
-    keyId: enclaveKeyId,
+    publicKey: enclaveKeys.publicKeyRaw.toString('hex'),
+    keyId: enclaveKeys.keyId,
   };
 
-  const nsmDocWithKey = await getNsmAttestationWithPubkey(enclavePublicKeyRaw);
+  const nsmDocWithKey = await getNsmAttestationWithPubkey(enclaveKeys.publicKeyRaw);
   if (nsmDocWithKey) {
     response.attestationDocument = nsmDocWithKey.toString('base64');
   }
@@ -537,10 +578,37 @@ async function handleProve(request: VsockRequest): Promise<VsockResponse> {
   const requestId = request.requestId || '';
   const encryptedPayload = request.encryptedPayload;
 
+  const metadataResult = validateProveMetadata(request.metadata, {
+    lenient: VSOCK_LENIENT_METADATA,
+    maxTimeoutMs: PROVE_TIMEOUT_MS,
+    allowDisableZk: ALLOW_DISABLE_ZK,
+    encrypted: encryptedPayload !== undefined,
+  });
+  if (!metadataResult.ok) {
+    logError('Prove metadata rejected', {
+      action: 'enclave.metadata.rejected', requestId,
+      code: metadataResult.code, keys: metadataResult.rejectedKeys,
+    });
+    return errorResponse(requestId, metadataResult.code, metadataResult.error, {
+      supportedKeys: metadataResult.supportedKeys,
+    });
+  }
+  if (metadataResult.ignoredKeys.length > 0) {
+    logInfo('Ignoring unknown prove metadata keys (lenient mode)', {
+      action: 'enclave.metadata.ignored', requestId, keys: metadataResult.ignoredKeys,
+    });
+  }
+  const proveOptions = metadataResult.metadata;
+  // Informational only: echoed back so the parent can correlate multi-tenant traffic
+  const echo = proveOptions.tenant ? { tenant: proveOptions.tenant } : {};
+
   // If encrypted payload present, decrypt to get { circuitId, inputs }
   if (encryptedPayload) {
     try {
-      const decrypted = decryptPayload(encryptedPayload);
+      if (!enclaveKeys) {
+        throw new EnclaveError('KEY_NOT_INITIALIZED', 'Key pair not initialized');
+      }
+      const decrypted = openEnvelope(encryptedPayload, enclaveKeys);
       const decryptedData = JSON.parse(decrypted);
       circuitId = decryptedData.circuitId || circuitId;
       if (decryptedData.inputs) {
@@ -550,27 +618,40 @@ async function handleProve(request: VsockRequest): Promise<VsockResponse> {
         action: 'enclave.decrypt.success', requestId, keyId: encryptedPayload.keyId,
       });
     } catch (err: any) {
+      // The cause stays in the enclave log; the response only says which step failed
       logError('Encrypted payload decryption failed', {
-        action: 'enclave.decrypt.failed', requestId, error: err.message,
+        action: 'enclave.decrypt.failed', requestId, error: err.message, cause: err.cause?.message,
       });
-      return { type: 'error', requestId, error: `Decryption failed: ${err.message}` };
+      if (err instanceof EnclaveError) {
+        return errorResponse(requestId, err.code, err.message, echo);
+      }
+      return errorResponse(requestId, 'DECRYPTION_FAILED', 'Decrypted payload is not valid JSON', echo);
     }
   }
 
   if (!circuitId) {
-    return { type: 'error', requestId, error: 'Missing circuitId' };
+    return errorResponse(requestId, 'BAD_REQUEST', 'Missing circuitId', echo);
   }
   if (!inputs || typeof inputs !== 'object') {
-    return { type: 'error', requestId, error: 'Missing inputs' };
+    return errorResponse(requestId, 'BAD_REQUEST', 'Missing inputs', echo);
   }
 
   try {
-    const result = await generateProof(circuitId, inputs, requestId);
+    const queuedAt = Date.now();
+    const [proveCircuitId, proveInputs] = [circuitId, inputs];
+    const result = await proveLimiter.run(() => {
+      logInfo('Prove slot acquired', {
+        action: 'enclave.prove.dequeued', requestId, waitedMs: Date.now() - queuedAt,
+      });
+      return generateProof(proveCircuitId, proveInputs, requestId, proveOptions);
+    }, proveOptions.priority);
     const response: VsockResponse = {
       type: 'proof',
       requestId,
+      ...echo,
       proof: result.proof,
       publicInputs: result.publicInputs,
+      proveSettings: result.settings,
       timing: result.timing,
     };
     if (result.attestationDocument) {
@@ -578,136 +659,167 @@ async function handleProve(request: VsockRequest): Promise<VsockResponse> {
     }
     return response;
   } catch (err: any) {
-    if (err.message?.includes('timed out') || err.message?.includes('TIMEOUT')) {
-      return { type: 'error', requestId, error: `Proof generation timed out after ${PROVE_TIMEOUT_MS / 1000}s` };
+    if (err instanceof CommandError && err.result.stalled) {
+      return errorResponse(requestId, 'STALLED', err.message, echo);
+    }
+    if (err instanceof CommandError && err.result.timedOut) {
+      const timeoutMs = proveOptions.timeoutMs ?? PROVE_TIMEOUT_MS;
+      return errorResponse(
+        requestId, 'PROVE_TIMEOUT', `Proof generation timed out after ${timeoutMs / 1000}s`, echo,
+      );
+    }
+    if (err instanceof EnclaveError) {
+      logError('Proof generation failed', {
+        action: 'enclave.prove.failed', requestId, tenant: proveOptions.tenant, code: err.code, error: err.message,
+      });
+      return errorResponse(requestId, err.code, err.message, echo);
+    }
+    if (err instanceof CommandError) {
+      return errorResponse(requestId, 'PROVE_FAILED', err.message, echo);
     }
     logError('Unexpected error in handleProve', {
       action: 'enclave.prove.error', error: err.message, stack: err.stack,
     });
-    return { type: 'error', requestId, error: err.message || 'Internal error' };
+    return errorResponse(requestId, 'INTERNAL', err.message || 'Internal error', echo);
   }
 }
 
-async function handleAttestation(request: VsockRequest): Promise<VsockResponse> {
+/** See attestation.ts for the request and response shapes */
+function handleAttestationRequest(request: VsockRequest): Promise<VsockResponse> {
+  return handleAttestation(request, {
+    attest: getNsmAttestation,
+    nsmAvailable: () => fs.existsSync(NSM_DEVICE),
+    signingKey,
+  });
+}
+
+/**
+ * Verify a proof produced elsewhere against the circuit's VK. An invalid
+ * proof is a normal `verify` response with isValid false, not an error; a bb
+ * run that gave no verdict (not started, killed, timed out) is an error.
+ */
+async function handleVerify(request: VsockRequest): Promise<VsockResponse> {
   const requestId = request.requestId || '';
-  const proofHashHex = request.proofHash || '';
+  const fields = validateVerifyRequest(request);
+  if (!fields.ok) {
+    return errorResponse(requestId, 'BAD_REQUEST', fields.error);
+  }
+
+  let paths: CircuitPaths;
+  try {
+    paths = getCircuitPaths(fields.circuitId);
+  } catch (err: any) {
+    return errorResponse(requestId, err.code ?? 'INTERNAL', err.message);
+  }
+  if (!fs.existsSync(paths.vk)) {
+    return errorResponse(requestId, 'CIRCUIT_UNAVAILABLE', `Circuit artifact missing: vk at ${paths.vk}`);
+  }
 
+  const workdir = await createWorkDir(WORK_DIR_BASE, requestId);
   try {
-    const userData = proofHashHex
-      ? Buffer.from(proofHashHex.replace(/^0x/, ''), 'hex')
-      : Buffer.alloc(0);
-    const nsmDoc = await getNsmAttestation(userData);
-    if (nsmDoc) {
-      return {
-        type: 'attestation',
-        requestId,
-        attestationDocument: nsmDoc.toString('base64'),
-      };
+    const proofFile = path.join(workdir, 'proof');
+    const publicInputsFile = path.join(workdir, 'public_inputs');
+    await fsp.writeFile(proofFile, fields.proof);
+    await fsp.writeFile(publicInputsFile, fields.publicInputs);
+
+    const verifyCmd = [
+      'verify',
+      '-p', proofFile,
+      '-i', publicInputsFile,
+      '-k', paths.vk,
+      '--oracle_hash', 'keccak',
+    ];
+    if (fields.disableZk) verifyCmd.push('--disable_zk');
+    try {
+      const run = await runCommand('bb verify', 'bb', verifyCmd, {
+        timeoutMs: VERIFY_TIMEOUT_MS,
+        env: { ...process.env, HOME: '/root' },
+        workDir: workdir,
+        onEvent: logCommandEvent(requestId),
+      });
+      logInfo('Proof verified', { action: 'enclave.verify.valid', requestId, circuitId: fields.circuitId });
+      return { type: 'verify', requestId, isValid: true, timing: { verifyMs: run.durationMs } };
+    } catch (err: any) {
+      if (!(err instanceof CommandError)) throw err;
+      if (err.result.timedOut) {
+        return errorResponse(
+          requestId, 'VERIFY_TIMEOUT', `Proof verification timed out after ${VERIFY_TIMEOUT_MS / 1000}s`,
+        );
+      }
+      if (!exitedNormally(err.result)) {
+        logError('bb verify did not run to completion', {
+          action: 'enclave.verify.error', requestId, circuitId: fields.circuitId,
+          signal: err.result.signal, error: err.message,
+        });
+        return errorResponse(requestId, 'INTERNAL', err.message);
+      }
+      // bb verify exits non-zero for proofs that do not verify, including malformed ones
+      logInfo('Proof rejected', {
+        action: 'enclave.verify.invalid', requestId, circuitId: fields.circuitId,
+        exitCode: err.result.exitCode, stderr: err.result.stderr,
+      });
+      return { type: 'verify', requestId, isValid: false, timing: { verifyMs: err.result.durationMs } };
     }
-    return { type: 'error', requestId, error: 'NSM device not available' };
   } catch (err: any) {
-    return { type: 'error', requestId, error: err.message };
+    logError('Unexpected error in handleVerify', {
+      action: 'enclave.verify.error', requestId, error: err.message, stack: err.stack,
+    });
+    return errorResponse(requestId, 'INTERNAL', err.message || 'Internal error');
+  } finally {
+    await fsp.rm(workdir, { recursive: true, force: true }).catch(() => {});
+  }
+}
+
+/** Circuits present in the image, including incomplete ones, for the parent's allow-list */
+async function handleListCircuits(request: VsockRequest): Promise<VsockResponse> {
+  const requestId = request.requestId || '';
+  try {
+    return { type: 'circuits', requestId, circuits: await listCircuits(CIRCUIT_BASE_DIR) };
+  } catch (err: any) {
+    logError('Listing circuits failed', { action: 'enclave.circuits.error', requestId, error: err.message });
+    return errorResponse(requestId, 'INTERNAL', `Cannot list circuits: ${err.message}`);
   }
 }
 
+/**
+ * Full detail behind a redacted error response, for operators on the parent
+ * host. detail is null once the entry has aged out of the in-memory log.
+ */
+function handleErrorDetail(request: VsockRequest): VsockResponse {
+  const requestId = request.requestId || '';
+  const errorId = request.errorId;
+  if (typeof errorId !== 'string' || !ERROR_ID_PATTERN.test(errorId)) {
+    return errorResponse(requestId, 'BAD_REQUEST', 'errorId must be a UUID');
+  }
+  return { type: 'errorDetail', requestId, errorId, detail: errorLog.get(errorId.toLowerCase()) ?? null };
+}
+
 async function dispatch(request: VsockRequest): Promise<VsockResponse> {
   switch (request.type) {
     case 'health':
       return handleHealth(request);
     case 'prove':
+      if (shutdown.draining) {
+        return errorResponse(
+          request.requestId || '', 'SHUTTING_DOWN', 'Enclave is shutting down; not accepting new proofs',
+        );
+      }
       return handleProve(request);
     case 'attestation':
-      return handleAttestation(request);
+      return handleAttestationRequest(request);
     case 'getPublicKey':
       return handleGetPublicKeyAsync(request);
+    case 'verify':
+      return handleVerify(request);
+    case 'listCircuits':
+      return handleListCircuits(request);
+    case 'errorDetail':
+      return handleErrorDetail(request);
     default:
-      return {
-        type: 'error',
-        requestId: request.requestId || '',
-        error: `Unknown request type: '${request.type}'`,
-      };
+      return errorResponse(request.requestId || '', 'BAD_REQUEST', `Unknown request type: '${request.type}'`);
   }
 }
 
-// ─────────────────────────────────────────────────────────────
-// Connection handler
-// ─────────────────────────────────────────────────────────────
-
-function handleConnection(socket: net.Socket, addr: string): void {
-  logInfo('Connection accepted', { action: 'enclave.connection.accepted', addr });
-
-  const chunks: Buffer[] = [];
-  let responded = false;
-
-  socket.setTimeout(5000);
-
-  socket.on('data', (chunk: Buffer) => {
-    chunks.push(chunk);
-  });
-
-  const processRequest = async () => {
-    if (responded) return;
-    responded = true;
-
-    const raw = Buffer.concat(chunks);
-    if (raw.length === 0) {
-      logError('Empty request received', { action: 'enclave.request.empty' });
-      socket.end();
-      return;
-    }
-
-    logInfo('Request received', { action: 'enclave.request.received', bytes: raw.length });
-
-    let request: VsockRequest;
-    try {
-      request = JSON.parse(raw.toString('utf-8'));
-    } catch (err: any) {
-      logError('Invalid JSON in request', { action: 'enclave.request.invalid', error: err.message });
-      const errorResponse = { type: 'error', requestId: '', error: `Invalid JSON: ${err.message}` };
-      socket.end(JSON.stringify(errorResponse));
-      return;
-    }
-
-    logInfo('Request dispatched', {
-      action: 'enclave.request.dispatched',
-      type: request.type,
-      requestId: request.requestId,
-    });
-
-    try {
-      const response = await dispatch(request);
-      logInfo('Response sent', {
-        action: 'enclave.response.sent',
-        type: response.type,
-        requestId: response.requestId,
-      });
-      socket.end(JSON.stringify(response));
-    } catch (err: any) {
-      logError('Unhandled error in connection handler', {
-        action: 'enclave.connection.error', error: err.message, stack: err.stack,
-      });
-      const errorResponse = { type: 'error', requestId: '', error: `Server error: ${err.message}` };
-      try {
-        socket.end(JSON.stringify(errorResponse));
-      } catch {
-        // Ignore write errors
-      }
-    }
-  };
-
-  socket.on('end', processRequest);
-  socket.on('timeout', processRequest);
-
-  socket.on('error', (err: Error) => {
-    logError('Socket error', { action: 'enclave.connection.error', error: err.message });
-    socket.destroy();
-  });
-
-  socket.on('close', () => {
-    logInfo('Connection closed', { action: 'enclave.connection.closed' });
-  });
-}
-
 // ─────────────────────────────────────────────────────────────
 // TCP fallback server (local testing)
 // ─────────────────────────────────────────────────────────────
@@ -717,26 +829,81 @@ function runTcpFallback(): void {
     action: 'enclave.server.starting', host: '127.0.0.1', port: TCP_FALLBACK_PORT,
   });
 
-  const server = net.createServer({ allowHalfOpen: true }, (socket) => {
-    const addr = `${socket.remoteAddress}:${socket.remotePort}`;
-    handleConnection(socket, addr);
-  });
+  const context = {
+    // Every request counts as in flight so a shutdown waits for it
+    dispatch: (request: VsockRequest) => shutdown.track(() => dispatch(request)),
+    stats: serverStats,
+    log,
+    detailLevel: getErrorDetailLevel(),
+    jsonLimits: getJsonLimits(),
+    errors: errorLog,
+  };
+  let server: net.Server;
+  let restartTimer: NodeJS.Timeout | undefined;
+
+  // Supervised listener: a server error re-creates it with exponential backoff,
+  // giving up (and exiting, so the enclave is restarted) after MAX_SERVER_RESTARTS.
+  // Never re-created once shutdown has started.
+  const start = () => {
+    restartTimer = undefined;
+    if (shutdown.draining) return;
+    server = net.createServer({ allowHalfOpen: true }, (socket) => {
+      const addr = `${socket.remoteAddress}:${socket.remotePort}`;
+      connectionPool.serve(socket, addr, context);
+    });
 
-  server.listen(TCP_FALLBACK_PORT, '127.0.0.1', () => {
-    logInfo('TCP fallback server listening', {
-      action: 'enclave.server.listening', port: TCP_FALLBACK_PORT,
+    server.listen(TCP_FALLBACK_PORT, '127.0.0.1', () => {
+      logInfo('TCP fallback server listening', {
+        action: 'enclave.server.listening', port: TCP_FALLBACK_PORT,
+      });
     });
-  });
 
-  server.on('error', (err: Error) => {
-    logError('TCP server error', { action: 'enclave.server.error', error: err.message });
-  });
+    server.on('error', (err: Error) => {
+      logError('TCP server error', { action: 'enclave.server.error', error: err.message });
+      server.close();
+      if (shutdown.draining) return;
+
+      if (serverStats.serverRestarts >= MAX_SERVER_RESTARTS) {
+        logError('TCP server restart limit reached', {
+          action: 'enclave.server.fatal', serverRestarts: serverStats.serverRestarts,
+        });
+        process.exit(1);
+      }
+      const delayMs = SERVER_RESTART_BACKOFF_MS * 2 ** serverStats.serverRestarts;
+      serverStats.serverRestarts += 1;
+      logInfo('Restarting TCP server', {
+        action: 'enclave.server.restarting', delayMs, serverRestarts: serverStats.serverRestarts,
+      });
+      restartTimer = setTimeout(start, delayMs);
+    });
+  };
+
+  start();
 
-  process.on('SIGINT', () => {
-    logInfo('TCP fallback server shutting down', { action: 'enclave.server.stopping' });
+  // Stop accepting connections, let in-flight requests finish within the grace
+  // period, then exit. A second signal exits immediately.
+  const stop = async (signal: string) => {
+    if (shutdown.draining) {
+      logError('Second signal during shutdown; exiting now', { action: 'enclave.server.killed', signal });
+      process.exit(1);
+    }
+    logInfo('TCP fallback server shutting down', {
+      action: 'enclave.server.stopping', signal,
+      activeRequests: shutdown.activeRequests, graceMs: SHUTDOWN_GRACE_MS,
+    });
+    clearTimeout(restartTimer);
     server.close();
-    process.exit(0);
-  });
+    const drained = await shutdown.drain(SHUTDOWN_GRACE_MS);
+    if (!drained) {
+      logError('Shutdown grace period elapsed with requests in flight', {
+        action: 'enclave.server.drain_timeout', activeRequests: shutdown.activeRequests,
+      });
+    }
+    logInfo('TCP fallback server stopped', { action: 'enclave.server.stopped', drained });
+    process.exit(drained ? 0 : 1);
+  };
+  process.on('SIGTERM', () => void stop('SIGTERM'));
+  process.on('SIGINT', () => void stop('SIGINT'));
 }
 
 // ─────────────────────────────────────────────────────────────
@@ -769,41 +936,64 @@ function runVsockServer(): void {
 // Entry point
 // ─────────────────────────────────────────────────────────────
 
-function main(): void {
+async function main(): Promise<void> {
   logInfo('Enclave server starting', {
     action: 'enclave.started',
     nodeVersion: process.version,
     platform: process.platform,
   });
-  logInfo('Circuit base directory', { action: 'enclave.config', path: CIRCUIT_BASE_DIR });
-  logInfo('Available circuits', { action: 'enclave.config', circuits: Object.keys(CIRCUITS) });
+  // Nothing is written under the circuits directory, so a read-only mount is fine; log which it is
+  const circuitsWriteError = await checkWritable(CIRCUIT_BASE_DIR);
+  logInfo('Circuit base directory', {
+    action: 'enclave.config', path: CIRCUIT_BASE_DIR, readOnly: circuitsWriteError !== null,
+  });
+  logInfo('State directory', { action: 'enclave.config', path: STATE_DIR });
+
+  // Fail now rather than on the first prove if the scratch directory is unusable
+  const workDir = await prepareWorkDirBase(WORK_DIR_BASE);
+  logInfo('Work directory base', { action: 'enclave.config', ...workDir });
+  logInfo('Concurrency limits', {
+    action: 'enclave.config', vsockWorkers: connectionPool.workers, proveConcurrency: proveLimiter.concurrency,
+    priorityAgingMs: proveLimiter.agingMs,
+  });
+  if (connectionPool.workers <= proveLimiter.concurrency) {
+    // Every worker could be held by a prove, leaving none for health checks
+    logError('VSOCK_WORKERS should exceed PROVE_CONCURRENCY', {
+      action: 'enclave.config', vsockWorkers: connectionPool.workers, proveConcurrency: proveLimiter.concurrency,
+    });
+  }
 
-  // Verify circuit artifacts at startup
-  for (const [circuitId, meta] of Object.entries(CIRCUITS)) {
-    const base = path.join(CIRCUIT_BASE_DIR, meta.dir);
-    const bytecode = path.join(base, 'target', meta.bytecode);
-    const vk = path.join(base, 'target', meta.vk);
-    if (fs.existsSync(bytecode) && fs.existsSync(vk)) {
-      logInfo('Circuit artifacts OK', { action: 'enclave.circuit.ok', circuit_id: circuitId });
-    } else {
-      logError('Circuit artifacts missing', {
-        action: 'enclave.circuit.missing',
-        circuit_id: circuitId,
-        bytecode_exists: fs.existsSync(bytecode),
-        vk_exists: fs.existsSync(vk),
-      });
-    }
+  // Serve every compiled circuit under the base dir; artifacts are validated during discovery
+  const discovery = await discoverCircuits(CIRCUIT_BASE_DIR, MAX_ARTIFACT_BYTES);
+  for (const { dir, reason } of discovery.skipped) {
+    logError('Circuit directory skipped', { action: 'enclave.circuit.skipped', dir, reason });
   }
+  if (discovery.libraries.length > 0) {
+    logInfo('Noir library packages skipped', { action: 'enclave.circuit.libraries', dirs: discovery.libraries });
+  }
+  for (const circuitId of Object.keys(discovery.circuits)) {
+    logInfo('Circuit artifacts OK', { action: 'enclave.circuit.ok', circuit_id: circuitId });
+  }
+  const missing = Object.keys(KNOWN_CIRCUITS).filter((circuitId) => !discovery.circuits[circuitId]);
+  if (missing.length > 0) {
+    // Still registered; prove re-checks artifacts and answers CIRCUIT_UNAVAILABLE
+    logError('Circuit artifacts missing', { action: 'enclave.circuit.missing', circuits: missing });
+  }
+  CIRCUITS = { ...KNOWN_CIRCUITS, ...discovery.circuits };
+  logInfo('Available circuits', { action: 'enclave.config', circuits: Object.keys(CIRCUITS) });
 
   // Generate X25519 key pair for E2E encryption
-  const { publicKey, privateKey } = crypto.generateKeyPairSync('x25519');
-  enclavePrivateKey = privateKey;
-  const publicKeyDer = publicKey.export({ type: 'spki', format: 'der' }) as Buffer;
-  enclavePublicKeyRaw = publicKeyDer.subarray(12); // Strip SPKI header (12 bytes)
-  enclaveKeyId = crypto.createHash('sha256').update(enclavePublicKeyRaw).digest('hex').slice(0, 16);
-  logInfo('X25519 key pair generated', { action: 'enclave.keygen.done', keyId: enclaveKeyId });
+  enclaveKeys = generateEnclaveKeyPair();
+  logInfo('X25519 key pair generated', { action: 'enclave.keygen.done', keyId: enclaveKeys.keyId });
+  signingKey = generateSigningKey();
+  logInfo('Ed25519 signing key generated', {
+    action: 'enclave.keygen.done', publicKey: signingKey.publicKeyRaw.toString('hex'),
+  });
 
   runVsockServer();
 }
 
-main();
+main().catch((err) => {
+  logError('Enclave server failed to start', { action: 'enclave.start.failed', error: err.message, stack: err.stack });
+  process.exit(1);
+});
@@ -0,0 +1,186 @@
+/**
+ * External command runner for the enclave proving pipeline (bb prove / bb verify).
+ *
+ * Every run produces exactly one CommandEvent — argv, duration, exit status and
+ * output sizes — which the server logs as `enclave.command.completed` and also
+ * uses for its timing breakdown, so logs and timings share one measurement.
+ *
+ * Besides the overall timeout, a run can have a stall window: if the child's
+ * CPU time (from /proc/<pid>/stat) and its output both stay flat for that long,
+ * it is killed early and reported as stalled.
+ */
+
+import { execFile } from 'node:child_process';
+import { readFileSync } from 'node:fs';
+
+export interface CommandEvent {
+  step: string;
+  /** Full argv (binary first) with work dir paths redacted */
+  argv: string[];
+  durationMs: number;
+  exitCode: number | null;
+  signal: string | null;
+  timedOut: boolean;
+  stalled: boolean;
+  stdoutBytes: number;
+  stderrBytes: number;
+}
+
+export interface CommandResult extends CommandEvent {
+  stdout: string;
+  stderr: string;
+}
+
+export interface RunCommandOptions {
+  timeoutMs: number;
+  env?: NodeJS.ProcessEnv;
+  /** Per-request work dir; paths under it are replaced with `<workdir>` in the event argv */
+  workDir?: string;
+  onEvent?: (event: CommandEvent) => void;
+  /** Kill the command when neither CPU time nor output advances for this long (0 or unset: off) */
+  stallWindowMs?: number;
+}
+
+/** Thrown when the command exits non-zero, is killed, times out or stalls */
+export class CommandError extends Error {
+  constructor(message: string, readonly result: CommandResult) {
+    super(message);
+    this.name = 'CommandError';
+  }
+}
+
+/**
+ * Whether the command ran and exited on its own, so its exit code is its
+ * answer. False when it could not be started, was killed by a signal, timed
+ * out or stalled.
+ */
+export function exitedNormally(result: CommandEvent): boolean {
+  return result.exitCode !== null && !result.timedOut && !result.stalled;
+}
+
+/** Replace the per-request work dir prefix so logs never carry request-specific paths */
+export function redactArgv(argv: string[], workDir?: string): string[] {
+  if (!workDir) return [...argv];
+  return argv.map((arg) => (arg.startsWith(workDir) ? '<workdir>' + arg.slice(workDir.length) : arg));
+}
+
+export const DEFAULT_STALL_WINDOW_MS = 30_000;
+
+/** Stall window for bb prove from PROVE_STALL_WINDOW_MS; unset or not a positive integer uses the default */
+export function getStallWindowMs(env: NodeJS.ProcessEnv = process.env): number {
+  const value = parseInt(env.PROVE_STALL_WINDOW_MS ?? '', 10);
+  return Number.isFinite(value) && value > 0 ? value : DEFAULT_STALL_WINDOW_MS;
+}
+
+/**
+ * Decides from periodic samples whether a process has made no progress for a
+ * whole window. Progress is any change in CPU ticks or output bytes; when CPU
+ * time cannot be read the detector never reports a stall.
+ */
+export class StallDetector {
+  private lastCpuTicks: number | null = null;
+  private lastOutputBytes = 0;
+  private lastProgressAt: number;
+
+  constructor(private readonly windowMs: number, startedAt: number) {
+    this.lastProgressAt = startedAt;
+  }
+
+  /** @returns true once the process has been idle for at least the window */
+  sample(now: number, cpuTicks: number | null, outputBytes: number): boolean {
+    if (cpuTicks === null) {
+      this.lastProgressAt = now;
+      return false;
+    }
+    if (cpuTicks !== this.lastCpuTicks || outputBytes !== this.lastOutputBytes) {
+      this.lastCpuTicks = cpuTicks;
+      this.lastOutputBytes = outputBytes;
+      this.lastProgressAt = now;
+      return false;
+    }
+    return now - this.lastProgressAt >= this.windowMs;
+  }
+}
+
+/** utime + stime of a process in clock ticks, or null when /proc is unavailable */
+export function readProcessCpuTicks(pid: number): number | null {
+  try {
+    const stat = readFileSync(`/proc/${pid}/stat`, 'utf-8');
+    // Fields after the parenthesised command name start at field 3 (state); utime and stime are 14 and 15
+    const fields = stat.slice(stat.lastIndexOf(')') + 2).split(' ');
+    const ticks = Number(fields[11]) + Number(fields[12]);
+    return Number.isFinite(ticks) ? ticks : null;
+  } catch {
+    return null;
+  }
+}
+
+export function runCommand(
+  step: string,
+  file: string,
+  args: string[],
+  options: RunCommandOptions,
+): Promise<CommandResult> {
+  const started = Date.now();
+
+  return new Promise((resolve, reject) => {
+    let stalled = false;
+    let stallTimer: NodeJS.Timeout | undefined;
+
+    const child = execFile(file, args, { timeout: options.timeoutMs, env: options.env }, (error, stdout, stderr) => {
+      clearInterval(stallTimer);
+      const err = error as (NodeJS.ErrnoException & { killed?: boolean; signal?: string | null; code?: number | string }) | null;
+      const timedOut = !stalled && Boolean(err?.killed) && Date.now() - started >= options.timeoutMs;
+
+      const result: CommandResult = {
+        step,
+        argv: redactArgv([file, ...args], options.workDir),
+        durationMs: Date.now() - started,
+        exitCode: err ? (typeof err.code === 'number' ? err.code : null) : 0,
+        signal: err?.signal ?? null,
+        timedOut,
+        stalled,
+        stdoutBytes: Buffer.byteLength(stdout ?? ''),
+        stderrBytes: Buffer.byteLength(stderr ?? ''),
+        stdout: stdout ?? '',
+        stderr: stderr ?? '',
+      };
+
+      const { stdout: _stdout, stderr: _stderr, ...event } = result;
+      options.onEvent?.(event);
+
+      if (!err) {
+        resolve(result);
+      } else if (stalled) {
+        reject(new CommandError(
+          `${step} stalled: no CPU or output progress for ${options.stallWindowMs! / 1000}s`,
+          result,
+        ));
+      } else if (timedOut) {
+        reject(new CommandError(`${step} timed out after ${options.timeoutMs / 1000}s`, result));
+      } else if (result.exitCode === null && !result.signal) {
+        reject(new CommandError(`${step} could not be started: ${err.message}`, result));
+      } else {
+        const status = result.exitCode !== null ? `exit ${result.exitCode}` : `signal ${result.signal}`;
+        reject(new CommandError(`${step} failed (${status}): ${result.stderr}`, result));
+      }
+    });
+
+    const stallWindowMs = options.stallWindowMs ?? 0;
+    if (stallWindowMs > 0 && child.pid !== undefined) {
+      const pid = child.pid;
+      const detector = new StallDetector(stallWindowMs, started);
+      let outputBytes = 0;
+      child.stdout?.on('data', (chunk) => { outputBytes += chunk.length; });
+      child.stderr?.on('data', (chunk) => { outputBytes += chunk.length; });
+
+      stallTimer = setInterval(() => {
+        if (detector.sample(Date.now(), readProcessCpuTicks(pid), outputBytes)) {
+          stalled = true;
+          clearInterval(stallTimer);
+          child.kill('SIGTERM');
+        }
+      }, Math.min(1000, Math.max(10, Math.floor(stallWindowMs / 4))));
+    }
+  });
+}
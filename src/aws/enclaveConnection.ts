@@ -0,0 +1,453 @@
+/**
+ * Connection handling for the Nitro Enclave vsock server (src/aws/enclave-server.ts).
+ *
+ * The server module starts listening on import, so the per-connection logic
+ * lives here: it takes the dispatcher, logger and counters as arguments and can
+ * be driven over any socket (or with raw request bytes) in tests.
+ */
+
+import type * as net from 'node:net';
+import { randomUUID } from 'node:crypto';
+import {
+  errorResponse,
+  redactErrorResponse,
+  validateRequestId,
+  sanitizeRequestId,
+  generateRequestId,
+} from './enclaveProtocol.js';
+import type { EnclaveErrorResponse, ErrorDetailLevel } from './enclaveProtocol.js';
+import { ByteCounter, KeyedByteCounter } from './byteCounter.js';
+import type { ErrorLog } from './errorLog.js';
+import { checkJsonLimits } from './jsonGuard.js';
+import type { JsonLimits } from './jsonGuard.js';
+
+// ─────────────────────────────────────────────────────────────
+// Types
+// ─────────────────────────────────────────────────────────────
+
+export interface VsockRequest {
+  type: string;
+  circuitId?: string;
+  inputs?: Record<string, any>;
+  requestId?: string;
+  encryptedPayload?: {
+    ephemeralPublicKey: string;
+    iv: string;
+    ciphertext: string;
+    authTag: string;
+    keyId: string;
+  };
+  proofHash?: string;
+  nonce?: string;
+  proof?: string;
+  publicInputs?: string[];
+  disableZk?: boolean;
+  errorId?: string;
+  metadata?: Record<string, unknown>;
+}
+
+export interface VsockResponse {
+  type: string;
+  requestId: string;
+  [key: string]: unknown;
+}
+
+export type Dispatch = (request: VsockRequest) => Promise<VsockResponse>;
+
+export type Logger = (level: 'info' | 'error', msg: string, extra?: Record<string, unknown>) => void;
+
+/** Counters reported in the health response, since process start */
+export interface ServerStats {
+  /** Handler exceptions that were turned into INTERNAL error responses */
+  handlerPanics: number;
+  /** Times the listener was re-created after a server error */
+  serverRestarts: number;
+  /** Request and response bytes across all connections */
+  transfer: ByteCounter;
+  /** The same bytes split by request type; requests without a usable type count as 'invalid' */
+  transferByType: KeyedByteCounter;
+}
+
+export function createServerStats(): ServerStats {
+  return {
+    handlerPanics: 0,
+    serverRestarts: 0,
+    transfer: new ByteCounter(),
+    transferByType: new KeyedByteCounter(),
+  };
+}
+
+export interface ConnectionContext {
+  dispatch: Dispatch;
+  stats: ServerStats;
+  log: Logger;
+  /** Error detail policy applied to every error response (see redactErrorResponse) */
+  detailLevel: ErrorDetailLevel;
+  /** Size, depth and token limits checked before a body is parsed */
+  jsonLimits: JsonLimits;
+  /** Keeps each error's full detail under its errorId for the errorDetail lookup */
+  errors: ErrorLog;
+}
+
+// ─────────────────────────────────────────────────────────────
+// Request processing
+// ─────────────────────────────────────────────────────────────
+
+/** What processRawRequest learned about a request, for per-type accounting */
+export interface RequestInfo {
+  /** request.type, or INVALID_REQUEST_TYPE when the body has no usable type */
+  type: string;
+}
+
+export const INVALID_REQUEST_TYPE = 'invalid';
+
+const REQUEST_TYPE_LABEL = /^[A-Za-z][A-Za-z0-9_]{0,31}$/;
+
+/**
+ * Turn one raw request into its response. Never rejects: malformed input
+ * becomes BAD_REQUEST and any exception escaping the dispatcher is counted as
+ * a handler panic and answered with INTERNAL, so one bad request cannot take
+ * the enclave process down.
+ *
+ * Every error response gets an errorId; its full message is logged and kept in
+ * context.errors under that id, and the returned message follows the
+ * context's detail policy.
+ *
+ * @param info receives the request type once the body is parsed
+ * @returns null for an empty request (the connection is closed without a reply)
+ */
+export async function processRawRequest(
+  raw: Buffer,
+  context: ConnectionContext,
+  info: RequestInfo = { type: INVALID_REQUEST_TYPE },
+): Promise<VsockResponse | null> {
+  const response = await buildResponse(raw, context, info);
+  if (!response || response.type !== 'error' || typeof response.code !== 'string') {
+    return response;
+  }
+
+  const errorId = randomUUID();
+  context.log('error', 'Request failed', {
+    action: 'enclave.error.detail',
+    errorId,
+    requestId: response.requestId,
+    code: response.code,
+    error: response.error,
+  });
+  context.errors.record({
+    errorId,
+    requestId: response.requestId,
+    code: response.code,
+    error: String(response.error),
+    at: new Date().toISOString(),
+  });
+  return redactErrorResponse(response as EnclaveErrorResponse, context.detailLevel, errorId);
+}
+
+async function buildResponse(raw: Buffer, context: ConnectionContext, info: RequestInfo): Promise<VsockResponse | null> {
+  const { dispatch, stats, log } = context;
+
+  if (raw.length === 0) {
+    log('error', 'Empty request received', { action: 'enclave.request.empty' });
+    return null;
+  }
+
+  log('info', 'Request received', { action: 'enclave.request.received', bytes: raw.length });
+
+  const limitError = checkJsonLimits(raw, context.jsonLimits);
+  if (limitError) {
+    const requestId = generateRequestId();
+    log('error', 'Request rejected before parsing', { action: 'enclave.request.rejected', requestId, error: limitError });
+    return errorResponse(requestId, 'BAD_REQUEST', limitError);
+  }
+
+  let request: VsockRequest;
+  try {
+    request = JSON.parse(raw.toString('utf-8'));
+  } catch (err: any) {
+    const requestId = generateRequestId();
+    log('error', 'Invalid JSON in request', { action: 'enclave.request.invalid', requestId, error: err.message });
+    return errorResponse(requestId, 'BAD_REQUEST', `Invalid JSON: ${err.message}`);
+  }
+  if (typeof request !== 'object' || request === null || Array.isArray(request)) {
+    const requestId = generateRequestId();
+    log('error', 'Request is not a JSON object', { action: 'enclave.request.invalid', requestId });
+    return errorResponse(requestId, 'BAD_REQUEST', 'Request must be a JSON object');
+  }
+  if (typeof request.type === 'string' && REQUEST_TYPE_LABEL.test(request.type)) {
+    info.type = request.type;
+  }
+
+  const requestIdError = validateRequestId(request.requestId);
+  if (requestIdError) {
+    const requestId = generateRequestId();
+    log('error', 'Invalid requestId', {
+      action: 'enclave.request.invalid',
+      requestId,
+      receivedRequestId: sanitizeRequestId(request.requestId),
+      error: requestIdError,
+    });
+    return errorResponse(requestId, 'BAD_REQUEST', requestIdError);
+  }
+
+  const requestId = request.requestId as string;
+  log('info', 'Request dispatched', {
+    action: 'enclave.request.dispatched',
+    type: request.type,
+    requestId,
+  });
+
+  try {
+    return await dispatch(request);
+  } catch (err: any) {
+    stats.handlerPanics += 1;
+    log('error', 'Unhandled error in request handler', {
+      action: 'enclave.handler.panic',
+      type: request.type,
+      requestId,
+      error: err?.message ?? String(err),
+      stack: err?.stack,
+      handlerPanics: stats.handlerPanics,
+    });
+    return errorResponse(requestId, 'INTERNAL', `Server error: ${err?.message ?? String(err)}`);
+  }
+}
+
+// ─────────────────────────────────────────────────────────────
+// Socket wiring
+// ─────────────────────────────────────────────────────────────
+
+const IDLE_TIMEOUT_MS = 5000;
+const NEWLINE = 0x0a;
+
+/** Whether a first line is a whole JSON object, which opts the connection into line framing */
+function isJsonObjectLine(line: Buffer, limits: JsonLimits): boolean {
+  if (checkJsonLimits(line, limits) !== null) return false;
+  try {
+    const value = JSON.parse(line.toString('utf-8'));
+    return typeof value === 'object' && value !== null && !Array.isArray(value);
+  } catch {
+    return false;
+  }
+}
+
+/**
+ * Serve one connection. The first line decides the framing:
+ *
+ * - newline-delimited: the first line is a complete JSON object. Every
+ *   `\n`-terminated line is then a request and is answered with a
+ *   `\n`-terminated response, in request order, while the connection stays open;
+ * - one-shot: anything else (a pretty-printed body, or no newline at all).
+ *   Everything up to half-close (or idleness) is one request, answered
+ *   without a newline, exactly as before line framing existed.
+ *
+ * Line requests are dispatched as soon as they are read; only the response
+ * writes are ordered. Our side is closed once the peer has stopped sending and
+ * every response is written. A failing request only produces an error
+ * response; later requests on the same connection are still served.
+ */
+export function handleConnection(socket: net.Socket, addr: string, context: ConnectionContext): void {
+  const { log, stats } = context;
+  log('info', 'Connection accepted', { action: 'enclave.connection.accepted', addr });
+
+  // The request currently being read. Past the size limit its bytes are only
+  // counted: the request is rejected on size without being parsed.
+  let pending: Buffer[] = [];
+  let pendingBytes = 0;
+  let framing: 'undecided' | 'lines' | 'oneShot' = 'undecided';
+  let queue: Promise<void> = Promise.resolve();
+  let inFlight = 0;
+  let finished = false;
+
+  socket.setTimeout(IDLE_TIMEOUT_MS);
+
+  const write = (response: VsockResponse | null, info: RequestInfo, requestBytes: number, delimited: boolean) => {
+    const byType = stats.transferByType.get(info.type);
+    byType.countReceived(requestBytes);
+    if (!response) return;
+
+    const payload = JSON.stringify(response) + (delimited ? '\n' : '');
+    const responseBytes = Buffer.byteLength(payload);
+    stats.transfer.countSent(responseBytes);
+    byType.countSent(responseBytes);
+    log('info', 'Response sent', {
+      action: 'enclave.response.sent',
+      type: response.type,
+      requestType: info.type,
+      requestId: response.requestId,
+      requestBytes,
+      responseBytes,
+    });
+    socket.write(payload);
+  };
+
+  const closeIfDone = () => {
+    if (finished && inFlight === 0 && !socket.destroyed) socket.end();
+  };
+
+  // Requests run concurrently; responses go out strictly in request order
+  const enqueue = (delimited: boolean) => {
+    const raw = Buffer.concat(pending);
+    const requestBytes = pendingBytes + (delimited ? 1 : 0);
+    pending = [];
+    pendingBytes = 0;
+
+    inFlight += 1;
+    stats.transfer.countReceived(requestBytes);
+    const info: RequestInfo = { type: INVALID_REQUEST_TYPE };
+    const response = processRawRequest(raw, context, info);
+    queue = queue
+      .then(async () => write(await response, info, requestBytes, delimited))
+      .catch((err: any) => {
+        log('error', 'Failed to write response', { action: 'enclave.connection.error', error: err?.message });
+        socket.destroy();
+      })
+      .finally(() => {
+        inFlight -= 1;
+        closeIfDone();
+      });
+  };
+
+  const append = (part: Buffer) => {
+    if (pendingBytes <= context.jsonLimits.maxBytes) pending.push(part);
+    pendingBytes += part.length;
+  };
+
+  socket.on('data', (chunk: Buffer) => {
+    if (framing === 'oneShot') {
+      append(chunk);
+      return;
+    }
+    let start = 0;
+    for (let newline = chunk.indexOf(NEWLINE); newline !== -1; newline = chunk.indexOf(NEWLINE, start)) {
+      append(chunk.subarray(start, newline));
+      start = newline + 1;
+      if (framing === 'undecided' && pendingBytes > 0) {
+        const complete = pendingBytes <= context.jsonLimits.maxBytes
+          && isJsonObjectLine(Buffer.concat(pending), context.jsonLimits);
+        if (!complete) {
+          // Not a request on its own line: keep reading the whole stream as one request
+          framing = 'oneShot';
+          append(chunk.subarray(newline));
+          return;
+        }
+        framing = 'lines';
+      }
+      if (pendingBytes === 0) {
+        stats.transfer.countReceived(1); // blank line between requests
+      } else {
+        enqueue(true);
+      }
+    }
+    if (start < chunk.length) append(chunk.subarray(start));
+  });
+
+  const onDone = () => {
+    if (finished) return;
+    finished = true;
+    if (pendingBytes > 0) enqueue(false);
+    closeIfDone();
+  };
+
+  socket.on('end', onDone);
+  socket.on('timeout', () => {
+    // A long prove is not idleness; the timer restarts once its response is written
+    if (inFlight === 0) onDone();
+  });
+
+  socket.on('error', (err: Error) => {
+    log('error', 'Socket error', { action: 'enclave.connection.error', error: err.message });
+    socket.destroy();
+  });
+
+  socket.on('close', () => {
+    log('info', 'Connection closed', { action: 'enclave.connection.closed' });
+  });
+}
+
+// ─────────────────────────────────────────────────────────────
+// Connection workers
+// ─────────────────────────────────────────────────────────────
+
+/**
+ * A connection keeps its worker while its prove waits for a prove slot, so the
+ * default leaves room for health checks behind a running and a queued prove.
+ */
+export const DEFAULT_VSOCK_WORKERS = 4;
+
+export function getVsockWorkers(env: NodeJS.ProcessEnv = process.env): number {
+  const value = parseInt(env.VSOCK_WORKERS ?? '', 10);
+  return Number.isFinite(value) && value > 0 ? value : DEFAULT_VSOCK_WORKERS;
+}
+
+interface QueuedConnection {
+  socket: net.Socket;
+  start: () => void;
+}
+
+/**
+ * Bounds how many connections are served at once (VSOCK_WORKERS). Connections
+ * over the bound are accepted but not read until a worker frees up, in arrival
+ * order; a worker is held until its connection closes.
+ */
+export class ConnectionPool {
+  private active = 0;
+  private readonly waiting: QueuedConnection[] = [];
+
+  constructor(readonly workers: number) {}
+
+  /** Connections being served */
+  get busy(): number {
+    return this.active;
+  }
+
+  /** Connections waiting for a worker */
+  get queued(): number {
+    return this.waiting.length;
+  }
+
+  serve(socket: net.Socket, addr: string, context: ConnectionContext): void {
+    if (this.active < this.workers) {
+      this.start(socket, addr, context);
+      return;
+    }
+
+    // Until handleConnection takes over, a reset from the peer must not go unhandled
+    const onError = (err: Error) => {
+      context.log('error', 'Socket error', { action: 'enclave.connection.error', error: err.message });
+      socket.destroy();
+    };
+    const onClose = () => {
+      const index = this.waiting.indexOf(queued);
+      if (index !== -1) this.waiting.splice(index, 1);
+    };
+    // Unread bytes hold back 'end', so this only fires for a peer that left without sending anything
+    const onEnd = () => {
+      onClose();
+      socket.end();
+    };
+    const queued: QueuedConnection = {
+      socket,
+      start: () => {
+        socket.off('error', onError);
+        socket.off('close', onClose);
+        socket.off('end', onEnd);
+        this.start(socket, addr, context);
+      },
+    };
+    socket.on('error', onError);
+    socket.on('close', onClose);
+    socket.on('end', onEnd);
+    this.waiting.push(queued);
+    context.log('info', 'Connection queued', { action: 'enclave.connection.queued', addr, queued: this.waiting.length });
+  }
+
+  private start(socket: net.Socket, addr: string, context: ConnectionContext): void {
+    this.active += 1;
+    socket.once('close', () => {
+      this.active -= 1;
+      this.waiting.shift()?.start();
+    });
+    handleConnection(socket, addr, context);
+  }
+}
@@ -0,0 +1,106 @@
+/**
+ * Enclave side of the E2E payload encryption (client side: src/tee/teeKeyExchange.ts).
+ *
+ * Kept out of enclave-server.ts so decryption can be tested without NSM or a
+ * running server. Wire format: see EncryptedEnvelope in src/tee/types.ts.
+ */
+
+import * as crypto from 'node:crypto';
+import { EnclaveError } from './enclaveProtocol.js';
+
+/** SubjectPublicKeyInfo prefix for a raw 32-byte X25519 public key */
+const X25519_SPKI_HEADER = Buffer.from('302a300506032b656e032100', 'hex');
+
+export interface EnclaveKeyPair {
+  privateKey: crypto.KeyObject;
+  /** Raw 32-byte X25519 public key, bound into the getPublicKey attestation as public_key */
+  publicKeyRaw: Buffer;
+  /** First 16 hex chars of SHA-256(publicKeyRaw); lets clients detect rotation */
+  keyId: string;
+}
+
+export interface EncryptedPayload {
+  ephemeralPublicKey: string;
+  iv: string;
+  ciphertext: string;
+  authTag: string;
+  keyId: string;
+}
+
+export function keyPairFromPrivateKey(privateKey: crypto.KeyObject): EnclaveKeyPair {
+  const publicKeyDer = crypto.createPublicKey(privateKey).export({ type: 'spki', format: 'der' }) as Buffer;
+  const publicKeyRaw = publicKeyDer.subarray(X25519_SPKI_HEADER.length);
+  const keyId = crypto.createHash('sha256').update(publicKeyRaw).digest('hex').slice(0, 16);
+  return { privateKey, publicKeyRaw, keyId };
+}
+
+export function generateEnclaveKeyPair(): EnclaveKeyPair {
+  return keyPairFromPrivateKey(crypto.generateKeyPairSync('x25519').privateKey);
+}
+
+/** SubjectPublicKeyInfo prefix for a raw 32-byte Ed25519 public key */
+const ED25519_SPKI_HEADER = Buffer.from('302a300506032b6570032100', 'hex');
+
+/** Enclave-held Ed25519 key, bound into `attestation` documents as public_key */
+export interface EnclaveSigningKey {
+  privateKey: crypto.KeyObject;
+  /** Raw 32-byte Ed25519 public key */
+  publicKeyRaw: Buffer;
+}
+
+export function generateSigningKey(): EnclaveSigningKey {
+  const { privateKey, publicKey } = crypto.generateKeyPairSync('ed25519');
+  const publicKeyDer = publicKey.export({ type: 'spki', format: 'der' }) as Buffer;
+  return { privateKey, publicKeyRaw: publicKeyDer.subarray(ED25519_SPKI_HEADER.length) };
+}
+
+export function importX25519PublicKey(rawHex: string): crypto.KeyObject {
+  const der = Buffer.concat([X25519_SPKI_HEADER, Buffer.from(rawHex, 'hex')]);
+  return crypto.createPublicKey({ key: der, format: 'der', type: 'spki' });
+}
+
+const HEX_FIELDS: Array<[keyof EncryptedPayload, number | null]> = [
+  ['ephemeralPublicKey', 32],
+  ['iv', 12],
+  ['authTag', 16],
+  ['ciphertext', null],
+];
+
+function isHexOfLength(value: unknown, bytes: number | null): boolean {
+  if (typeof value !== 'string' || !/^([0-9a-fA-F]{2})+$/.test(value)) return false;
+  return bytes === null || value.length === bytes * 2;
+}
+
+/**
+ * Decrypt an envelope: ECDH(enclave private, ephemeral public) → SHA-256 → AES-256-GCM.
+ *
+ * A keyId mismatch is KEY_ROTATED so the client can refetch the key; every
+ * other failure is DECRYPTION_FAILED with a fixed message, so callers learn
+ * nothing about which check failed. The cause is attached for logging only.
+ */
+export function openEnvelope(payload: EncryptedPayload, keys: EnclaveKeyPair): string {
+  if (payload?.keyId !== keys.keyId) {
+    throw new EnclaveError('KEY_ROTATED', `Key ID mismatch: expected ${keys.keyId}`);
+  }
+
+  try {
+    for (const [field, bytes] of HEX_FIELDS) {
+      if (!isHexOfLength(payload[field], bytes)) {
+        throw new Error(`malformed ${field}`);
+      }
+    }
+
+    const sharedSecret = crypto.diffieHellman({
+      publicKey: importX25519PublicKey(payload.ephemeralPublicKey),
+      privateKey: keys.privateKey,
+    });
+    const aesKey = crypto.createHash('sha256').update(sharedSecret).digest();
+
+    const decipher = crypto.createDecipheriv('aes-256-gcm', aesKey, Buffer.from(payload.iv, 'hex'));
+    decipher.setAuthTag(Buffer.from(payload.authTag, 'hex'));
+    const plaintext = Buffer.concat([decipher.update(Buffer.from(payload.ciphertext, 'hex')), decipher.final()]);
+    return plaintext.toString('utf-8');
+  } catch (err: any) {
+    throw new EnclaveError('DECRYPTION_FAILED', 'Decryption failed', { cause: err });
+  }
+}
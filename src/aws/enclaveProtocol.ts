@@ -0,0 +1,575 @@
+/**
+ * Vsock protocol rules for the Nitro Enclave server (src/aws/enclave-server.ts).
+ *
+ * Everything in this module is side-effect free so the request validation can be
+ * unit tested without starting the server, touching /dev/nsm or loading noir_js.
+ */
+
+import { randomUUID } from 'node:crypto';
+
+// ─────────────────────────────────────────────────────────────
+// Error codes
+// ─────────────────────────────────────────────────────────────
+
+/** Every machine-readable `code` a `{ type: "error" }` vsock response can carry */
+export const ENCLAVE_ERROR_CODES = [
+  'BAD_REQUEST',
+  'INVALID_METADATA',
+  'METADATA_OVERRIDE_REFUSED',
+  'DECRYPTION_FAILED',
+  'KEY_ROTATED',
+  'KEY_NOT_INITIALIZED',
+  'CIRCUIT_NOT_FOUND',
+  'CIRCUIT_UNAVAILABLE',
+  'WITNESS_UNSATISFIED',
+  'PROVE_FAILED',
+  'PROVE_TIMEOUT',
+  'STALLED',
+  'VERIFY_FAILED',
+  'VERIFY_TIMEOUT',
+  'ARTIFACT_INVALID',
+  'ATTESTATION_FAILED',
+  'ATTESTATION_UNAVAILABLE',
+  'SHUTTING_DOWN',
+  'INTERNAL',
+] as const;
+
+export type EnclaveErrorCode = (typeof ENCLAVE_ERROR_CODES)[number];
+
+/**
+ * Whether the parent may resend the same request without risking a duplicate
+ * or a guaranteed repeat failure. Exhaustive: an unmapped code fails to compile.
+ */
+export function isRetryable(code: EnclaveErrorCode): boolean {
+  switch (code) {
+    case 'PROVE_TIMEOUT':
+    case 'STALLED':
+    case 'VERIFY_TIMEOUT':
+    case 'ATTESTATION_FAILED':
+    case 'KEY_NOT_INITIALIZED':
+    case 'SHUTTING_DOWN':
+      return true;
+    case 'BAD_REQUEST':
+    case 'INVALID_METADATA':
+    case 'METADATA_OVERRIDE_REFUSED':
+    case 'DECRYPTION_FAILED':
+    case 'KEY_ROTATED':
+    case 'CIRCUIT_NOT_FOUND':
+    case 'CIRCUIT_UNAVAILABLE':
+    case 'WITNESS_UNSATISFIED':
+    case 'PROVE_FAILED':
+    case 'VERIFY_FAILED':
+    case 'ARTIFACT_INVALID':
+    case 'ATTESTATION_UNAVAILABLE':
+    case 'INTERNAL':
+      return false;
+    default: {
+      const unmapped: never = code;
+      return unmapped;
+    }
+  }
+}
+
+/** Error thrown inside the proving pipeline, carrying the code to report */
+export class EnclaveError extends Error {
+  constructor(readonly code: EnclaveErrorCode, message: string, options?: { cause?: unknown }) {
+    super(message, options);
+    this.name = 'EnclaveError';
+  }
+}
+
+export interface EnclaveErrorResponse {
+  type: 'error';
+  requestId: string;
+  code: EnclaveErrorCode;
+  error: string;
+  retryable: boolean;
+  [key: string]: unknown;
+}
+
+/** Build the `{ type: "error" }` response; retryable is always derived from the code */
+export function errorResponse(
+  requestId: string,
+  code: EnclaveErrorCode,
+  error: string,
+  extra: Record<string, unknown> = {},
+): EnclaveErrorResponse {
+  return { type: 'error', requestId, ...extra, code, error, retryable: isRetryable(code) };
+}
+
+// ─────────────────────────────────────────────────────────────
+// Request IDs
+// ─────────────────────────────────────────────────────────────
+
+/**
+ * requestId ends up in log lines and in work directory names, so it is limited
+ * to a short printable subset. Everything the parent generates (UUIDs,
+ * `health-<ms>`, `x402-<hex>`) fits.
+ */
+export const MAX_REQUEST_ID_LENGTH = 128;
+const REQUEST_ID_PATTERN = /^[A-Za-z0-9._:-]+$/;
+
+/** @returns an error message, or null if the requestId is acceptable */
+export function validateRequestId(raw: unknown): string | null {
+  if (typeof raw !== 'string' || raw.length === 0) {
+    return 'requestId must be a non-empty string';
+  }
+  if (raw.length > MAX_REQUEST_ID_LENGTH) {
+    return `requestId must not exceed ${MAX_REQUEST_ID_LENGTH} characters`;
+  }
+  if (!REQUEST_ID_PATTERN.test(raw)) {
+    return 'requestId may only contain letters, digits and . _ : -';
+  }
+  return null;
+}
+
+/** Log- and path-safe rendering of an arbitrary requestId value */
+export function sanitizeRequestId(raw: unknown): string {
+  const text = typeof raw === 'string' ? raw : '';
+  return text.slice(0, MAX_REQUEST_ID_LENGTH).replace(/[^A-Za-z0-9._:-]/g, '_');
+}
+
+/** Server-side id for responses to requests whose own id is missing or unusable */
+export function generateRequestId(): string {
+  return `srv-${randomUUID()}`;
+}
+
+// ─────────────────────────────────────────────────────────────
+// Error detail policy
+// ─────────────────────────────────────────────────────────────
+
+/**
+ * How much error detail leaves the enclave. 'public' (the default) replaces
+ * messages that can contain paths, bb stderr or stack details with a generic
+ * message and an errorId; the full message is only logged. 'full' (DETAIL_LEVEL=full,
+ * for development) returns messages unchanged.
+ */
+export type ErrorDetailLevel = 'public' | 'full';
+
+export function getErrorDetailLevel(env: NodeJS.ProcessEnv = process.env): ErrorDetailLevel {
+  return env.DETAIL_LEVEL === 'full' ? 'full' : 'public';
+}
+
+/** Generic message for a code, safe to return to any caller */
+export function publicErrorMessage(code: EnclaveErrorCode): string {
+  switch (code) {
+    case 'BAD_REQUEST': return 'Malformed request';
+    case 'INVALID_METADATA': return 'Invalid prove metadata';
+    case 'METADATA_OVERRIDE_REFUSED': return 'Prove metadata override refused';
+    case 'DECRYPTION_FAILED': return 'Encrypted payload could not be decrypted';
+    case 'KEY_ROTATED': return 'Enclave key has rotated; fetch the current public key and re-encrypt';
+    case 'KEY_NOT_INITIALIZED': return 'Enclave key pair is not initialized yet';
+    case 'CIRCUIT_NOT_FOUND': return 'Unknown circuit';
+    case 'CIRCUIT_UNAVAILABLE': return 'Circuit is not available on this enclave';
+    case 'WITNESS_UNSATISFIED': return 'Inputs do not satisfy the circuit constraints';
+    case 'PROVE_FAILED': return 'Proof generation failed';
+    case 'PROVE_TIMEOUT': return 'Proof generation timed out';
+    case 'STALLED': return 'Proof generation stopped making progress';
+    case 'VERIFY_FAILED': return 'Generated proof failed self-verification';
+    case 'VERIFY_TIMEOUT': return 'Proof verification timed out';
+    case 'ARTIFACT_INVALID': return 'Generated proof artifacts failed validation';
+    case 'ATTESTATION_FAILED': return 'Attestation could not be obtained';
+    case 'ATTESTATION_UNAVAILABLE': return 'Attestation is not available outside a Nitro Enclave';
+    case 'SHUTTING_DOWN': return 'Enclave is shutting down';
+    case 'INTERNAL': return 'Internal enclave error';
+    default: {
+      const unmapped: never = code;
+      return unmapped;
+    }
+  }
+}
+
+/**
+ * Codes whose message only describes the caller's own request (field names,
+ * supported values), so it is returned as-is even at the public level.
+ */
+const CALLER_FACING_CODES: ReadonlySet<EnclaveErrorCode> = new Set([
+  'BAD_REQUEST',
+  'INVALID_METADATA',
+  'METADATA_OVERRIDE_REFUSED',
+  'CIRCUIT_NOT_FOUND',
+]);
+
+/** Apply the detail policy to an error response, tagging it with errorId */
+export function redactErrorResponse(
+  response: EnclaveErrorResponse,
+  level: ErrorDetailLevel,
+  errorId: string,
+): EnclaveErrorResponse {
+  if (level === 'full' || CALLER_FACING_CODES.has(response.code)) {
+    return { ...response, errorId };
+  }
+  return { ...response, error: publicErrorMessage(response.code), errorId };
+}
+
+// ─────────────────────────────────────────────────────────────
+// Prove request metadata
+// ─────────────────────────────────────────────────────────────
+
+/** Options a parent may attach to a prove request via `metadata` */
+export interface ProveMetadata {
+  /** bb prove timeout in milliseconds (capped at the server maximum) */
+  timeoutMs?: number;
+  /** Return the proof without requesting an NSM attestation document */
+  skipAttestation?: boolean;
+  /** Run `bb verify` on the generated proof before responding */
+  verifyBeforeReturn?: boolean;
+  /** Hex nonce forwarded to the NSM attestation request */
+  nonce?: string;
+  /** Tenant the parent is relaying for; informational, echoed back in the response */
+  tenant?: string;
+  /** Scheduling hint from the parent */
+  priority?: ProvePriority;
+  /** Run bb in its low-memory mode (slower; for small enclaves) */
+  lowMemory?: boolean;
+  /**
+   * Produce a non-ZK proof; for internal testing only, it does not hide the
+   * witness. Refused unless the enclave allows it, and always for encrypted requests.
+   */
+  disableZk?: boolean;
+}
+
+/** bb settings a proof was actually generated with, echoed in the proof response */
+export interface ProveSettings {
+  lowMemory: boolean;
+  disableZk: boolean;
+}
+
+export function effectiveProveSettings(metadata: ProveMetadata): ProveSettings {
+  return {
+    lowMemory: metadata.lowMemory ?? false,
+    disableZk: metadata.disableZk ?? false,
+  };
+}
+
+export const PROVE_METADATA_KEYS: readonly (keyof ProveMetadata)[] = [
+  'timeoutMs',
+  'skipAttestation',
+  'verifyBeforeReturn',
+  'nonce',
+  'tenant',
+  'priority',
+  'lowMemory',
+  'disableZk',
+];
+
+/** NSM rejects nonces larger than 512 bytes */
+export const MAX_NONCE_BYTES = 512;
+
+/**
+ * Normalize a hex nonce for the NSM request: optional 0x prefix, non-empty,
+ * even length, at most MAX_NONCE_BYTES. Shared by prove metadata and
+ * attestation requests; `field` names the value in the error message.
+ * @returns lowercase hex without the 0x prefix
+ */
+export function parseNonce(raw: unknown, field: string): { ok: true; nonce: string } | { ok: false; error: string } {
+  const clean = typeof raw === 'string' ? raw.replace(/^0x/, '') : '';
+  if (clean.length === 0 || clean.length % 2 !== 0 || !/^[0-9a-fA-F]+$/.test(clean)) {
+    return { ok: false, error: `${field} must be a non-empty even-length hex string` };
+  }
+  if (clean.length / 2 > MAX_NONCE_BYTES) {
+    return { ok: false, error: `${field} must not exceed ${MAX_NONCE_BYTES} bytes` };
+  }
+  return { ok: true, nonce: clean.toLowerCase() };
+}
+
+export const PROVE_PRIORITIES = ['low', 'normal', 'high'] as const;
+export type ProvePriority = (typeof PROVE_PRIORITIES)[number];
+
+/** Tenants are free-form identifiers, but they end up in logs, so keep them short and plain */
+export const MAX_TENANT_LENGTH = 64;
+const TENANT_PATTERN = /^[A-Za-z0-9._:-]+$/;
+
+/**
+ * Keys that try to select the prover toolchain. These are refused even in
+ * lenient mode: the attestation only vouches for the binaries measured into
+ * the enclave image, so a proof made by any other binary must never leave it.
+ */
+const BINARY_OVERRIDE_KEYS = new Set(['bb', 'nargo', 'prover', 'bbPath', 'nargoPath', 'bbBinary', 'nargoBinary']);
+const BINARY_OVERRIDE_PATTERN = /(path|binary|bin)$/i;
+
+function isBinaryOverrideKey(key: string): boolean {
+  return BINARY_OVERRIDE_KEYS.has(key) || BINARY_OVERRIDE_PATTERN.test(key);
+}
+
+export type ProveMetadataResult =
+  | { ok: true; metadata: ProveMetadata; ignoredKeys: string[] }
+  | {
+    ok: false;
+    code: EnclaveErrorCode;
+    error: string;
+    supportedKeys: readonly string[];
+    rejectedKeys: string[];
+  };
+
+function reject(code: EnclaveErrorCode, error: string, rejectedKeys: string[]): ProveMetadataResult {
+  return { ok: false, code, error, supportedKeys: PROVE_METADATA_KEYS, rejectedKeys };
+}
+
+export interface ProveMetadataOptions {
+  lenient: boolean;
+  maxTimeoutMs: number;
+  /** Whether disableZk may be honoured at all (ALLOW_DISABLE_ZK=true); off by default */
+  allowDisableZk?: boolean;
+  /** The request carries an encryptedPayload */
+  encrypted?: boolean;
+}
+
+/**
+ * Validate the `metadata` object of a prove request against the accepted schema.
+ *
+ * Unknown keys are rejected unless `lenient` is set (VSOCK_LENIENT_METADATA=true),
+ * in which case they are reported back in `ignoredKeys`. Binary path overrides
+ * are always refused.
+ *
+ * `metadata` travels in plaintext outside the encrypted envelope, so the parent
+ * controls it. disableZk is refused for encrypted requests (a non-ZK proof would
+ * reveal the witness the encryption protects) and unless `allowDisableZk` is set.
+ */
+export function validateProveMetadata(
+  raw: unknown,
+  options: ProveMetadataOptions,
+): ProveMetadataResult {
+  if (raw === undefined || raw === null) {
+    return { ok: true, metadata: {}, ignoredKeys: [] };
+  }
+  if (typeof raw !== 'object' || Array.isArray(raw)) {
+    return reject('INVALID_METADATA', 'metadata must be a JSON object', []);
+  }
+
+  const entries = Object.entries(raw as Record<string, unknown>);
+
+  const overrideKeys = entries.map(([key]) => key).filter(isBinaryOverrideKey);
+  if (overrideKeys.length > 0) {
+    return reject(
+      'METADATA_OVERRIDE_REFUSED',
+      `Refusing prover binary override (${overrideKeys.join(', ')}): the enclave only runs the bb/nargo ` +
+        'binaries measured into its image, so attested proofs cannot come from caller-selected binaries',
+      overrideKeys,
+    );
+  }
+
+  const supported = new Set<string>(PROVE_METADATA_KEYS);
+  const unknownKeys = entries.map(([key]) => key).filter((key) => !supported.has(key));
+  if (unknownKeys.length > 0 && !options.lenient) {
+    return reject(
+      'INVALID_METADATA',
+      `Unknown metadata keys: ${unknownKeys.join(', ')}. Supported: ${PROVE_METADATA_KEYS.join(', ')}`,
+      unknownKeys,
+    );
+  }
+
+  const source = raw as Record<string, unknown>;
+  const metadata: ProveMetadata = {};
+
+  if (source.timeoutMs !== undefined) {
+    const timeoutMs = source.timeoutMs;
+    if (typeof timeoutMs !== 'number' || !Number.isInteger(timeoutMs) || timeoutMs <= 0) {
+      return reject('INVALID_METADATA', 'metadata.timeoutMs must be a positive integer', ['timeoutMs']);
+    }
+    if (timeoutMs > options.maxTimeoutMs) {
+      return reject(
+        'INVALID_METADATA',
+        `metadata.timeoutMs must not exceed ${options.maxTimeoutMs}`,
+        ['timeoutMs'],
+      );
+    }
+    metadata.timeoutMs = timeoutMs;
+  }
+
+  for (const key of ['skipAttestation', 'verifyBeforeReturn', 'lowMemory', 'disableZk'] as const) {
+    if (source[key] !== undefined) {
+      if (typeof source[key] !== 'boolean') {
+        return reject('INVALID_METADATA', `metadata.${key} must be a boolean`, [key]);
+      }
+      metadata[key] = source[key] as boolean;
+    }
+  }
+
+  if (metadata.disableZk) {
+    if (options.encrypted) {
+      return reject(
+        'METADATA_OVERRIDE_REFUSED',
+        'Refusing metadata.disableZk for an encrypted prove request: a non-ZK proof reveals the witness',
+        ['disableZk'],
+      );
+    }
+    if (!options.allowDisableZk) {
+      return reject('METADATA_OVERRIDE_REFUSED', 'metadata.disableZk is not enabled on this enclave', ['disableZk']);
+    }
+  }
+
+  if (source.nonce !== undefined) {
+    const nonce = parseNonce(source.nonce, 'metadata.nonce');
+    if (!nonce.ok) {
+      return reject('INVALID_METADATA', nonce.error, ['nonce']);
+    }
+    metadata.nonce = nonce.nonce;
+  }
+
+  if (source.tenant !== undefined) {
+    const tenant = source.tenant;
+    if (typeof tenant !== 'string' || tenant.length === 0 || tenant.length > MAX_TENANT_LENGTH) {
+      return reject(
+        'INVALID_METADATA',
+        `metadata.tenant must be a string of 1-${MAX_TENANT_LENGTH} characters`,
+        ['tenant'],
+      );
+    }
+    if (!TENANT_PATTERN.test(tenant)) {
+      return reject('INVALID_METADATA', 'metadata.tenant may only contain letters, digits and . _ : -', ['tenant']);
+    }
+    metadata.tenant = tenant;
+  }
+
+  if (source.priority !== undefined) {
+    if (!PROVE_PRIORITIES.includes(source.priority as ProvePriority)) {
+      return reject(
+        'INVALID_METADATA',
+        `metadata.priority must be one of: ${PROVE_PRIORITIES.join(', ')}`,
+        ['priority'],
+      );
+    }
+    metadata.priority = source.priority as ProvePriority;
+  }
+
+  return { ok: true, metadata, ignoredKeys: unknownKeys };
+}
+
+// ─────────────────────────────────────────────────────────────
+// Verify request
+// ─────────────────────────────────────────────────────────────
+
+export interface VerifyRequestFields {
+  circuitId: string;
+  proof: Buffer;
+  /** publicInputs entries concatenated, as bb expects in its public_inputs file */
+  publicInputs: Buffer;
+  /** The proof was generated with --disable_zk (proveSettings.disableZk), so bb verify needs it too */
+  disableZk: boolean;
+}
+
+export type VerifyRequestValidation =
+  | ({ ok: true } & VerifyRequestFields)
+  | { ok: false; error: string };
+
+/**
+ * How odd-length hex is handled: `strict` rejects it (proofs, where a missing
+ * nibble means a corrupt artifact); `lenient` left-pads one zero nibble
+ * (user-facing values such as public inputs, where clients drop leading zeros).
+ */
+export type HexPolicy = 'strict' | 'lenient';
+
+export type HexParseResult = { ok: true; bytes: Buffer } | { ok: false; error: string };
+
+/**
+ * Decode a hex field with an optional 0x prefix. `""` and bare `"0x"` decode
+ * to zero bytes under both policies; callers that need a value check for that.
+ * Errors name the field, the number of hex digits received and the policy.
+ */
+export function parseHex(raw: unknown, field: string, policy: HexPolicy): HexParseResult {
+  if (typeof raw !== 'string') {
+    return { ok: false, error: `${field} must be a hex string (${policy} policy)` };
+  }
+  let digits = raw.startsWith('0x') ? raw.slice(2) : raw;
+  if (!/^[0-9a-fA-F]*$/.test(digits)) {
+    return { ok: false, error: `${field} contains non-hex characters (${digits.length} characters received, ${policy} policy)` };
+  }
+  if (digits.length % 2 !== 0) {
+    if (policy === 'strict') {
+      return { ok: false, error: `${field} has odd length (${digits.length} hex digits received, strict policy requires an even length)` };
+    }
+    digits = '0' + digits;
+  }
+  return { ok: true, bytes: Buffer.from(digits, 'hex') };
+}
+
+/**
+ * Check the fields of a `verify` request: a circuitId, a non-empty hex proof
+ * (strict), publicInputs as an array of hex strings (lenient; the shape proof
+ * responses use, empty for circuits without public inputs) and an optional
+ * boolean disableZk.
+ */
+export function validateVerifyRequest(request: {
+  circuitId?: unknown;
+  proof?: unknown;
+  publicInputs?: unknown;
+  disableZk?: unknown;
+}): VerifyRequestValidation {
+  const { circuitId, proof, publicInputs, disableZk } = request;
+  if (typeof circuitId !== 'string' || circuitId.length === 0) {
+    return { ok: false, error: 'Missing circuitId' };
+  }
+  if (proof === undefined || proof === '' || proof === '0x') {
+    return { ok: false, error: 'Missing proof' };
+  }
+  const proofBytes = parseHex(proof, 'proof', 'strict');
+  if (!proofBytes.ok) {
+    return proofBytes;
+  }
+  if (!Array.isArray(publicInputs)) {
+    return { ok: false, error: 'publicInputs must be an array of hex strings' };
+  }
+  const inputs: Buffer[] = [];
+  for (const [index, value] of publicInputs.entries()) {
+    const parsed = parseHex(value, `publicInputs[${index}]`, 'lenient');
+    if (!parsed.ok) {
+      return parsed;
+    }
+    inputs.push(parsed.bytes);
+  }
+  if (disableZk !== undefined && typeof disableZk !== 'boolean') {
+    return { ok: false, error: 'disableZk must be a boolean' };
+  }
+
+  return {
+    ok: true,
+    circuitId,
+    proof: proofBytes.bytes,
+    publicInputs: Buffer.concat(inputs),
+    disableZk: disableZk ?? false,
+  };
+}
+
+// ─────────────────────────────────────────────────────────────
+// Attestation request
+// ─────────────────────────────────────────────────────────────
+
+/** NSM rejects user_data larger than 512 bytes */
+export const MAX_USER_DATA_BYTES = 512;
+
+export type AttestationRequestValidation =
+  | { ok: true; userData: Buffer; nonce?: string }
+  | { ok: false; error: string };
+
+/**
+ * Check the fields of an `attestation` request: proofHash becomes user_data,
+ * is parsed leniently and may be absent or empty; nonce is optional and parsed
+ * like the prove metadata nonce.
+ */
+export function validateAttestationRequest(request: {
+  proofHash?: unknown;
+  nonce?: unknown;
+}): AttestationRequestValidation {
+  const { proofHash } = request;
+  let userData = Buffer.alloc(0);
+  if (proofHash !== undefined) {
+    const parsed = parseHex(proofHash, 'proofHash', 'lenient');
+    if (!parsed.ok) {
+      return parsed;
+    }
+    userData = parsed.bytes;
+    if (userData.length > MAX_USER_DATA_BYTES) {
+      return { ok: false, error: `proofHash must not exceed ${MAX_USER_DATA_BYTES} bytes` };
+    }
+  }
+
+  if (request.nonce === undefined) {
+    return { ok: true, userData };
+  }
+  const nonce = parseNonce(request.nonce, 'nonce');
+  if (!nonce.ok) {
+    return { ok: false, error: nonce.error };
+  }
+  return { ok: true, userData, nonce: nonce.nonce };
+}
@@ -0,0 +1,44 @@
+/**
+ * Recent error details by errorId, so an operator can look up the full
+ * message behind a redacted error response with an `errorDetail` vsock
+ * request. Only the parent host can reach the vsock port, so the lookup is not
+ * exposed to the callers who see the redacted responses.
+ *
+ * Details are kept in memory only and bounded: the oldest entries drop out
+ * first, and the same details stay in the enclave.error.detail log lines.
+ */
+
+export const DEFAULT_ERROR_LOG_SIZE = 256;
+
+export interface ErrorDetail {
+  errorId: string;
+  /** requestId of the request that failed */
+  requestId: string;
+  code: string;
+  /** The unredacted message */
+  error: string;
+  /** ISO timestamp */
+  at: string;
+}
+
+export class ErrorLog {
+  private readonly entries = new Map<string, ErrorDetail>();
+
+  constructor(readonly capacity = DEFAULT_ERROR_LOG_SIZE) {}
+
+  get size(): number {
+    return this.entries.size;
+  }
+
+  record(detail: ErrorDetail): void {
+    this.entries.set(detail.errorId, detail);
+    // Maps iterate in insertion order, so the first key is the oldest
+    while (this.entries.size > this.capacity) {
+      this.entries.delete(this.entries.keys().next().value!);
+    }
+  }
+
+  get(errorId: string): ErrorDetail | undefined {
+    return this.entries.get(errorId);
+  }
+}
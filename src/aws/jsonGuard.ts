@@ -0,0 +1,108 @@
+/**
+ * Pre-parse guard for vsock request bodies.
+ *
+ * JSON.parse recurses on nesting and allocates for every value, so a small but
+ * deeply nested or token-dense body can burn CPU and stack before any
+ * validation runs. checkJsonLimits() scans the raw text in one linear pass,
+ * without allocating, and rejects bodies over the configured size, depth or
+ * token count before they are parsed.
+ */
+
+export interface JsonLimits {
+  /** Maximum request size in bytes */
+  maxBytes: number;
+  /** Maximum nesting of objects and arrays */
+  maxDepth: number;
+  /** Maximum number of tokens: objects, arrays, keys, strings, numbers and literals */
+  maxTokens: number;
+}
+
+export const DEFAULT_JSON_LIMITS: JsonLimits = {
+  maxBytes: 4 * 1024 * 1024,
+  maxDepth: 64,
+  maxTokens: 100_000,
+};
+
+function envInt(value: string | undefined, fallback: number): number {
+  const parsed = parseInt(value ?? '', 10);
+  return Number.isFinite(parsed) && parsed > 0 ? parsed : fallback;
+}
+
+/** Limits from VSOCK_MAX_REQUEST_BYTES, VSOCK_MAX_JSON_DEPTH and VSOCK_MAX_JSON_TOKENS */
+export function getJsonLimits(env: NodeJS.ProcessEnv = process.env): JsonLimits {
+  return {
+    maxBytes: envInt(env.VSOCK_MAX_REQUEST_BYTES, DEFAULT_JSON_LIMITS.maxBytes),
+    maxDepth: envInt(env.VSOCK_MAX_JSON_DEPTH, DEFAULT_JSON_LIMITS.maxDepth),
+    maxTokens: envInt(env.VSOCK_MAX_JSON_TOKENS, DEFAULT_JSON_LIMITS.maxTokens),
+  };
+}
+
+const QUOTE = 0x22;
+const BACKSLASH = 0x5c;
+const OPEN_BRACE = 0x7b;
+const CLOSE_BRACE = 0x7d;
+const OPEN_BRACKET = 0x5b;
+const CLOSE_BRACKET = 0x5d;
+const COMMA = 0x2c;
+const COLON = 0x3a;
+
+function isWhitespace(c: number): boolean {
+  return c === 0x20 || c === 0x09 || c === 0x0a || c === 0x0d;
+}
+
+/**
+ * Check a raw body against the limits. Syntax errors are left to JSON.parse;
+ * this only bounds the work it will do.
+ * @returns an error message, or null if the body is within all limits
+ */
+export function checkJsonLimits(raw: Buffer, limits: JsonLimits): string | null {
+  if (raw.length > limits.maxBytes) {
+    return `Request exceeds ${limits.maxBytes} bytes`;
+  }
+
+  let depth = 0;
+  let tokens = 0;
+  let inString = false;
+  let escaped = false;
+  let inScalar = false;
+
+  // Byte-wise scan is safe for UTF-8: multi-byte sequences never contain ASCII bytes
+  for (let i = 0; i < raw.length; i++) {
+    const c = raw[i];
+
+    if (inString) {
+      if (escaped) escaped = false;
+      else if (c === BACKSLASH) escaped = true;
+      else if (c === QUOTE) inString = false;
+      continue;
+    }
+
+    if (c === OPEN_BRACE || c === OPEN_BRACKET) {
+      depth++;
+      tokens++;
+      inScalar = false;
+      if (depth > limits.maxDepth) {
+        return `JSON nesting exceeds depth ${limits.maxDepth}`;
+      }
+    } else if (c === CLOSE_BRACE || c === CLOSE_BRACKET) {
+      // Stray closers are a syntax error for JSON.parse; never let them offset later openers
+      if (depth > 0) depth--;
+      inScalar = false;
+    } else if (c === QUOTE) {
+      inString = true;
+      tokens++;
+      inScalar = false;
+    } else if (c === COMMA || c === COLON || isWhitespace(c)) {
+      inScalar = false;
+    } else if (!inScalar) {
+      inScalar = true;
+      tokens++;
+    }
+
+    if (tokens > limits.maxTokens) {
+      return `JSON exceeds ${limits.maxTokens} tokens`;
+    }
+  }
+
+  return null;
+}
@@ -0,0 +1,121 @@
+/**
+ * Sanity checks on the files bb prove leaves in <workdir>/proof before the
+ * enclave hex-encodes them into a response.
+ *
+ * A short write (e.g. disk full mid-proof) otherwise produces a truncated proof
+ * that the parent forwards on-chain, where it fails far from the cause.
+ */
+
+/** Bytes per field element in bb's proof and public_inputs files */
+export const FIELD_BYTES = 32;
+
+export interface ProofSizeBounds {
+  minBytes: number;
+  maxBytes: number;
+}
+
+/** Wide enough for UltraHonk proofs of every circuit we ship; narrow with PROOF_MIN_BYTES / PROOF_MAX_BYTES */
+export const DEFAULT_PROOF_SIZE_BOUNDS: ProofSizeBounds = {
+  minBytes: 1024,
+  maxBytes: 64 * 1024,
+};
+
+export function getProofSizeBounds(env: NodeJS.ProcessEnv = process.env): ProofSizeBounds {
+  const min = parseInt(env.PROOF_MIN_BYTES ?? '', 10);
+  const max = parseInt(env.PROOF_MAX_BYTES ?? '', 10);
+  return {
+    minBytes: Number.isFinite(min) && min > 0 ? min : DEFAULT_PROOF_SIZE_BOUNDS.minBytes,
+    maxBytes: Number.isFinite(max) && max > 0 ? max : DEFAULT_PROOF_SIZE_BOUNDS.maxBytes,
+  };
+}
+
+interface AbiType {
+  kind: string;
+  length?: number;
+  type?: AbiType;
+  fields?: Array<AbiType | { name: string; type: AbiType }>;
+}
+
+/** Number of field elements an ABI type flattens to, or null for an unknown shape */
+function flattenedSize(type: AbiType | undefined): number | null {
+  if (!type || typeof type !== 'object') return null;
+  switch (type.kind) {
+    case 'field':
+    case 'integer':
+    case 'boolean':
+      return 1;
+    case 'string':
+      return typeof type.length === 'number' ? type.length : null;
+    case 'array': {
+      const inner = flattenedSize(type.type);
+      return inner !== null && typeof type.length === 'number' ? inner * type.length : null;
+    }
+    case 'struct':
+    case 'tuple': {
+      let total = 0;
+      for (const field of type.fields ?? []) {
+        const size = flattenedSize('kind' in field ? field : field.type);
+        if (size === null) return null;
+        total += size;
+      }
+      return total;
+    }
+    default:
+      return null;
+  }
+}
+
+/**
+ * Count the public input fields a compiled circuit declares: public parameters
+ * plus a public return value.
+ * @returns null when the ABI is missing or contains a type we cannot size
+ */
+export function countPublicInputFields(abi: unknown): number | null {
+  if (!abi || typeof abi !== 'object') return null;
+  const { parameters, return_type: returnType } = abi as {
+    parameters?: Array<{ type: AbiType; visibility?: string }>;
+    return_type?: { abi_type: AbiType; visibility?: string } | null;
+  };
+  if (!Array.isArray(parameters)) return null;
+
+  let total = 0;
+  for (const param of parameters) {
+    if (param.visibility !== 'public') continue;
+    const size = flattenedSize(param.type);
+    if (size === null) return null;
+    total += size;
+  }
+  if (returnType && returnType.visibility === 'public') {
+    const size = flattenedSize(returnType.abi_type);
+    if (size === null) return null;
+    total += size;
+  }
+  return total;
+}
+
+/**
+ * Validate proof and public input sizes.
+ * @param expectedPublicFields from countPublicInputFields(), or null to skip the count check
+ * @returns an error message, or null if the artifacts look complete
+ */
+export function validateProofArtifacts(
+  proofBytes: number,
+  publicInputBytes: number,
+  expectedPublicFields: number | null,
+  bounds: ProofSizeBounds,
+): string | null {
+  if (proofBytes < bounds.minBytes || proofBytes > bounds.maxBytes) {
+    return `proof is ${proofBytes} bytes, expected ${bounds.minBytes}-${bounds.maxBytes}`;
+  }
+  if (proofBytes % FIELD_BYTES !== 0) {
+    return `proof is ${proofBytes} bytes, not a multiple of ${FIELD_BYTES}`;
+  }
+  if (publicInputBytes % FIELD_BYTES !== 0) {
+    return `public_inputs is ${publicInputBytes} bytes, not a multiple of ${FIELD_BYTES}`;
+  }
+  const fields = publicInputBytes / FIELD_BYTES;
+  if (expectedPublicFields !== null && fields !== expectedPublicFields) {
+    return `public_inputs has ${fields} fields, circuit declares ${expectedPublicFields}`;
+  }
+  return null;
+}
@@ -0,0 +1,96 @@
+/**
+ * Bounds how many proofs the enclave generates at once. Connections are served
+ * concurrently (up to VSOCK_WORKERS, see ConnectionPool), so health and
+ * attestation requests are answered while a prove is running; only the
+ * nargo/bb work waits here, which keeps several concurrent bb processes from
+ * exhausting enclave memory.
+ */
+
+import type { ProvePriority } from './enclaveProtocol.js';
+
+export const DEFAULT_PROVE_CONCURRENCY = 1;
+
+export function getProveConcurrency(env: NodeJS.ProcessEnv = process.env): number {
+  const value = parseInt(env.PROVE_CONCURRENCY ?? '', 10);
+  return Number.isFinite(value) && value > 0 ? value : DEFAULT_PROVE_CONCURRENCY;
+}
+
+/** A waiting prove moves up one priority level per this much waiting */
+export const DEFAULT_PRIORITY_AGING_MS = 60_000;
+
+export function getPriorityAgingMs(env: NodeJS.ProcessEnv = process.env): number {
+  const value = parseInt(env.PROVE_PRIORITY_AGING_MS ?? '', 10);
+  return Number.isFinite(value) && value > 0 ? value : DEFAULT_PRIORITY_AGING_MS;
+}
+
+const PRIORITY_RANK: Record<ProvePriority, number> = { high: 0, normal: 1, low: 2 };
+
+interface Waiter {
+  rank: number;
+  enqueuedAt: number;
+  start: () => void;
+}
+
+export class ProveLimiter {
+  private active = 0;
+  /** In arrival order */
+  private readonly waiting: Waiter[] = [];
+
+  constructor(
+    readonly concurrency: number,
+    readonly agingMs = DEFAULT_PRIORITY_AGING_MS,
+    private readonly now: () => number = Date.now,
+  ) {}
+
+  /** Proofs currently holding a slot */
+  get running(): number {
+    return this.active;
+  }
+
+  /** Proofs waiting for a slot */
+  get queued(): number {
+    return this.waiting.length;
+  }
+
+  /**
+   * Run task once a slot is free. Waiting tasks start by priority (the
+   * metadata.priority hint), first come first served within a priority. A
+   * waiter gains one priority level per agingMs waited, so a steady stream of
+   * high-priority proves delays low-priority ones but cannot starve them.
+   */
+  async run<T>(task: () => Promise<T>, priority: ProvePriority = 'normal'): Promise<T> {
+    if (this.active >= this.concurrency) {
+      await new Promise<void>((start) => {
+        this.waiting.push({ rank: PRIORITY_RANK[priority], enqueuedAt: this.now(), start });
+      });
+    } else {
+      this.active += 1;
+    }
+
+    try {
+      return await task();
+    } finally {
+      const next = this.takeNext();
+      if (next) {
+        next.start(); // the slot passes straight to the next waiter
+      } else {
+        this.active -= 1;
+      }
+    }
+  }
+
+  /** Remove the waiter with the best aged rank; the earliest arrival wins ties */
+  private takeNext(): Waiter | undefined {
+    const now = this.now();
+    let best = -1;
+    let bestRank = Infinity;
+    this.waiting.forEach((waiter, index) => {
+      const rank = waiter.rank - Math.floor((now - waiter.enqueuedAt) / this.agingMs);
+      if (rank < bestRank) {
+        best = index;
+        bestRank = rank;
+      }
+    });
+    return best === -1 ? undefined : this.waiting.splice(best, 1)[0];
+  }
+}
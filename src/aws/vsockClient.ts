@@ -0,0 +1,220 @@
+/**
+ * Debug client for the enclave vsock protocol.
+ *
+ * Builds VsockRequest objects from CLI-style options and sends them over the
+ * same TCP leg the parent uses (vsock-bridge.py on the host, or the enclave's
+ * TCP fallback on 127.0.0.1:15000). Node.js cannot open AF_VSOCK sockets, so
+ * reaching a CID directly still requires the bridge.
+ *
+ * Request/response shapes come from src/tee/types.ts so this tool cannot drift
+ * from EnclaveClient. CLI entry point: scripts/vsock-client.ts
+ */
+
+import { connect } from 'node:net';
+import type { Duplex } from 'node:stream';
+import type { VsockRequest, VsockResponse } from '../tee/types.js';
+
+export type VsockRequestType = VsockRequest['type'];
+
+export const VSOCK_REQUEST_TYPES: readonly VsockRequestType[] = [
+  'health', 'prove', 'attestation', 'getPublicKey', 'verify', 'listCircuits', 'errorDetail',
+];
+
+export interface VsockClientOptions {
+  type: string;
+  requestId?: string;
+  circuitId?: string;
+  inputs?: Record<string, any>;
+  proofHash?: string;
+  nonce?: string;
+  proof?: string;
+  publicInputs?: string[];
+  disableZk?: boolean;
+  errorId?: string;
+  metadata?: Record<string, unknown>;
+}
+
+/**
+ * Build a protocol request from CLI options.
+ * @throws Error if the type is unknown or a required field is missing
+ */
+export function buildVsockRequest(options: VsockClientOptions): VsockRequest {
+  const type = options.type as VsockRequestType;
+  if (!VSOCK_REQUEST_TYPES.includes(type)) {
+    throw new Error(`Unknown request type '${options.type}'. Supported: ${VSOCK_REQUEST_TYPES.join(', ')}`);
+  }
+
+  const request: VsockRequest = {
+    type,
+    requestId: options.requestId || `cli-${type}-${Date.now()}`,
+  };
+
+  if (type === 'prove') {
+    if (!options.circuitId) {
+      throw new Error('prove requests require a circuitId');
+    }
+    if (!options.inputs || typeof options.inputs !== 'object') {
+      throw new Error('prove requests require an inputs object');
+    }
+    request.circuitId = options.circuitId;
+    request.inputs = options.inputs;
+  }
+
+  if (type === 'verify') {
+    if (!options.circuitId) {
+      throw new Error('verify requests require a circuitId');
+    }
+    if (!options.proof) {
+      throw new Error('verify requests require a proof');
+    }
+    request.circuitId = options.circuitId;
+    request.proof = options.proof;
+    request.publicInputs = options.publicInputs ?? [];
+    if (options.disableZk) request.disableZk = true;
+  }
+
+  if (type === 'errorDetail') {
+    if (!options.errorId) {
+      throw new Error('errorDetail requests require an errorId');
+    }
+    request.errorId = options.errorId;
+  }
+
+  if (type === 'attestation' && options.proofHash) {
+    request.proofHash = options.proofHash;
+  }
+
+  if (type === 'attestation' && options.nonce) {
+    request.nonce = options.nonce;
+  }
+
+  if (options.metadata) {
+    request.metadata = options.metadata;
+  }
+
+  return request;
+}
+
+/**
+ * Parse a request file for --file: one request object, or an array of them for
+ * framed mode. Requests are passed through unchanged, so fields the flag
+ * builder does not know (encryptedPayload, for one) reach the enclave as
+ * written; only the shape is checked here and the enclave validates the rest.
+ * @throws Error if the JSON is not a request object or an array of them
+ */
+export function parseVsockRequestFile(text: string): VsockRequest[] {
+  const parsed: unknown = JSON.parse(text);
+  const requests = Array.isArray(parsed) ? parsed : [parsed];
+  if (requests.length === 0) {
+    throw new Error('Request file contains no requests');
+  }
+  requests.forEach((request, index) => {
+    if (typeof request !== 'object' || request === null || Array.isArray(request)
+      || typeof (request as { type?: unknown }).type !== 'string') {
+      throw new Error(`Request ${index} in file must be an object with a string type`);
+    }
+  });
+  return requests as VsockRequest[];
+}
+
+/**
+ * Write one request to an already-connected stream and read the response.
+ * Uses the one-shot framing: the request is terminated by half-closing the
+ * stream and the response ends when the server closes its side.
+ */
+export function exchangeVsockRequest(stream: Duplex, request: VsockRequest): Promise<VsockResponse> {
+  return new Promise((resolve, reject) => {
+    const chunks: Buffer[] = [];
+
+    stream.on('data', (chunk: Buffer) => chunks.push(chunk));
+    stream.on('error', reject);
+    stream.on('end', () => {
+      const raw = Buffer.concat(chunks).toString('utf-8');
+      if (!raw) {
+        reject(new Error('Empty response from enclave'));
+        return;
+      }
+      try {
+        resolve(JSON.parse(raw) as VsockResponse);
+      } catch {
+        reject(new Error(`Invalid JSON from enclave: ${raw.substring(0, 200)}`));
+      }
+    });
+
+    stream.end(JSON.stringify(request));
+  });
+}
+
+/**
+ * Write several requests to an already-connected stream, one per line, then
+ * half-close it and read one newline-terminated response per request. The
+ * first line being a compact JSON object is what puts the enclave in line
+ * mode; it answers in request order, so responses line up with `requests`.
+ */
+export function exchangeVsockRequests(stream: Duplex, requests: VsockRequest[]): Promise<VsockResponse[]> {
+  return new Promise((resolve, reject) => {
+    const chunks: Buffer[] = [];
+
+    stream.on('data', (chunk: Buffer) => chunks.push(chunk));
+    stream.on('error', reject);
+    stream.on('end', () => {
+      const lines = Buffer.concat(chunks).toString('utf-8').split('\n').filter((line) => line.length > 0);
+      if (lines.length !== requests.length) {
+        reject(new Error(`Expected ${requests.length} responses from enclave, got ${lines.length}`));
+        return;
+      }
+      try {
+        resolve(lines.map((line) => JSON.parse(line) as VsockResponse));
+      } catch {
+        reject(new Error(`Invalid JSON from enclave: ${lines.join('\n').substring(0, 200)}`));
+      }
+    });
+
+    stream.end(requests.map((request) => JSON.stringify(request) + '\n').join(''));
+  });
+}
+
+function withConnection<T>(
+  host: string,
+  port: number,
+  timeoutMs: number,
+  exchange: (stream: Duplex) => Promise<T>,
+): Promise<T> {
+  return new Promise((resolve, reject) => {
+    const socket = connect({ host, port, allowHalfOpen: true });
+    socket.setTimeout(timeoutMs);
+    socket.on('timeout', () => {
+      socket.destroy();
+      reject(new Error(`Connection timeout after ${timeoutMs}ms`));
+    });
+    socket.on('connect', () => {
+      exchange(socket).then(resolve, reject);
+    });
+    socket.on('error', reject);
+  });
+}
+
+/** Connect to host:port, send the request and return the parsed response */
+export function sendVsockRequest(
+  host: string,
+  port: number,
+  request: VsockRequest,
+  timeoutMs = 120_000,
+): Promise<VsockResponse> {
+  return withConnection(host, port, timeoutMs, (stream) => exchangeVsockRequest(stream, request));
+}
+
+/** Connect to host:port and send the requests with line framing (see exchangeVsockRequests) */
+export function sendVsockRequests(
+  host: string,
+  port: number,
+  requests: VsockRequest[],
+  timeoutMs = 120_000,
+): Promise<VsockResponse[]> {
+  return withConnection(host, port, timeoutMs, (stream) => exchangeVsockRequests(stream, requests));
+}
+
+/** Process exit code for a response: 0 on success, 1 when the enclave reported an error */
+export function exitCodeForResponse(response: VsockResponse): number {
+  return response.type === 'error' ? 1 : 0;
+}
@@ -0,0 +1,123 @@
+/**
+ * Per-request scratch directories for the enclave prover.
+ *
+ * Everything the enclave writes lives under STATE_DIR (default: a `state`
+ * directory next to the circuits directory), so the circuits directory can be
+ * mounted read-only. bb writes witnesses and proofs under WORK_DIR_BASE
+ * (default: STATE_DIR/work); enclaves with a small root filesystem can point
+ * it at a larger scratch mount. The base is checked once at startup so a
+ * missing or read-only path fails immediately instead of on the first prove.
+ */
+
+import * as fsp from 'node:fs/promises';
+import * as path from 'node:path';
+import { constants as fsConstants } from 'node:fs';
+import type { Stats } from 'node:fs';
+import { sanitizeRequestId } from './enclaveProtocol.js';
+
+export interface WorkDirStatus {
+  path: string;
+  /** Bytes available to unprivileged writers, or null if statfs is unsupported */
+  freeBytes: number | null;
+}
+
+/** Resolve the directory for writable state from the environment */
+export function getStateDir(circuitsDir: string, env: NodeJS.ProcessEnv = process.env): string {
+  const configured = env.STATE_DIR?.trim();
+  return path.resolve(configured || path.join(path.dirname(path.resolve(circuitsDir)), 'state'));
+}
+
+/** Default work dir base under the state directory */
+export function defaultWorkDirBase(stateDir: string): string {
+  return path.join(stateDir, 'work');
+}
+
+/** Resolve the base directory from the environment */
+export function getWorkDirBase(defaultBase: string, env: NodeJS.ProcessEnv = process.env): string {
+  const configured = env.WORK_DIR_BASE?.trim();
+  return path.resolve(configured || defaultBase);
+}
+
+async function freeBytes(base: string): Promise<number | null> {
+  try {
+    const stats = await fsp.statfs(base);
+    return stats.bavail * stats.bsize;
+  } catch {
+    return null;
+  }
+}
+
+/**
+ * Probe whether dir accepts writes by creating and removing a directory, which
+ * also catches read-only mounts that permission bits do not show.
+ * @returns null when writable, otherwise the error code or message
+ */
+export async function probeWritable(dir: string): Promise<string | null> {
+  try {
+    const probe = await fsp.mkdtemp(path.join(dir, '.probe-'));
+    await fsp.rm(probe, { recursive: true, force: true });
+    return null;
+  } catch (err: any) {
+    return err.code || err.message;
+  }
+}
+
+/**
+ * Check whether dir accepts writes without writing anything: access(W_OK)
+ * fails with EROFS on a read-only mount and EACCES on permission bits. Used
+ * for the circuits directory, under which the enclave never creates files.
+ * @returns null when writable, otherwise the error code or message
+ */
+export async function checkWritable(dir: string): Promise<string | null> {
+  try {
+    await fsp.access(dir, fsConstants.W_OK);
+    return null;
+  } catch (err: any) {
+    return err.code || err.message;
+  }
+}
+
+/**
+ * Check that the base exists, is a directory and is writable.
+ * @throws Error naming WORK_DIR_BASE and the path when a check fails
+ */
+export async function checkWorkDirBase(base: string): Promise<WorkDirStatus> {
+  let stats: Stats;
+  try {
+    stats = await fsp.stat(base);
+  } catch {
+    throw new Error(`WORK_DIR_BASE ${base} does not exist`);
+  }
+  if (!stats.isDirectory()) {
+    throw new Error(`WORK_DIR_BASE ${base} is not a directory`);
+  }
+
+  const writeError = await probeWritable(base);
+  if (writeError) {
+    throw new Error(`WORK_DIR_BASE ${base} is not writable: ${writeError}`);
+  }
+
+  return { path: base, freeBytes: await freeBytes(base) };
+}
+
+/**
+ * Startup check of the base: the default under STATE_DIR is created when
+ * missing, while a configured WORK_DIR_BASE must already exist (it is usually
+ * a separate mount).
+ */
+export async function prepareWorkDirBase(base: string, env: NodeJS.ProcessEnv = process.env): Promise<WorkDirStatus> {
+  if (!env.WORK_DIR_BASE?.trim()) {
+    await fsp.mkdir(base, { recursive: true });
+  }
+  return checkWorkDirBase(base);
+}
+
+/** Current free space of the base, for the health response */
+export async function getWorkDirStatus(base: string): Promise<WorkDirStatus> {
+  return { path: base, freeBytes: await freeBytes(base) };
+}
+
+/** Create a fresh working directory for one request under the base */
+export function createWorkDir(base: string, requestId: string): Promise<string> {
+  return fsp.mkdtemp(path.join(base, `proof-${sanitizeRequestId(requestId)}-`));
+}
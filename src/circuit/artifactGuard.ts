@@ -0,0 +1,56 @@
+import * as fs from 'node:fs/promises';
+
+/** Upper bound for any single circuit artifact (compiled JSON or VK), overridable via MAX_ARTIFACT_BYTES */
+export const DEFAULT_MAX_ARTIFACT_BYTES = 64 * 1024 * 1024;
+
+const BASE64_PATTERN = /^[A-Za-z0-9+/]*={0,2}$/;
+
+export function getMaxArtifactBytes(env: NodeJS.ProcessEnv = process.env): number {
+  const parsed = env.MAX_ARTIFACT_BYTES ? parseInt(env.MAX_ARTIFACT_BYTES, 10) : NaN;
+  return Number.isInteger(parsed) && parsed > 0 ? parsed : DEFAULT_MAX_ARTIFACT_BYTES;
+}
+
+/**
+ * @throws Error naming the artifact, its size and the limit when it is too large
+ */
+export function assertArtifactSize(label: string, actualBytes: number, maxBytes: number): void {
+  if (actualBytes > maxBytes) {
+    throw new Error(`Circuit artifact too large: ${label} is ${actualBytes} bytes (limit ${maxBytes})`);
+  }
+}
+
+/**
+ * Check that a compiled circuit JSON carries a plausible base64 `bytecode` field.
+ * @throws Error if the field is missing, not base64, or longer than maxBytes
+ */
+export function validateBytecodeField(circuitJson: unknown, label: string, maxBytes: number): void {
+  const bytecode = (circuitJson as { bytecode?: unknown } | null)?.bytecode;
+  if (typeof bytecode !== 'string' || bytecode.length === 0) {
+    throw new Error(`Invalid circuit artifact: ${label} has no bytecode field`);
+  }
+  if (bytecode.length > maxBytes) {
+    throw new Error(`Invalid circuit artifact: ${label} bytecode is ${bytecode.length} chars (limit ${maxBytes})`);
+  }
+  if (bytecode.length % 4 !== 0 || !BASE64_PATTERN.test(bytecode)) {
+    throw new Error(`Invalid circuit artifact: ${label} bytecode is not valid base64`);
+  }
+}
+
+/**
+ * Read and parse a compiled circuit JSON, checking its size with stat() before
+ * reading so an oversized or corrupted artifact cannot exhaust memory.
+ */
+export async function readCircuitJson(filePath: string, maxBytes: number): Promise<Record<string, unknown>> {
+  const stat = await fs.stat(filePath);
+  assertArtifactSize(filePath, stat.size, maxBytes);
+
+  let circuitJson: Record<string, unknown>;
+  try {
+    circuitJson = JSON.parse(await fs.readFile(filePath, 'utf-8'));
+  } catch (error: any) {
+    throw new Error(`Invalid circuit artifact: ${filePath} is not valid JSON (${error.message})`);
+  }
+
+  validateBytecodeField(circuitJson, filePath, maxBytes);
+  return circuitJson;
+}
@@ -1,6 +1,7 @@
 import * as fs from 'node:fs/promises';
 import * as path from 'node:path';
 import * as crypto from 'node:crypto';
+import { assertArtifactSize, getMaxArtifactBytes } from './artifactGuard.js';
 
 // Circuit metadata mapping
 const CIRCUIT_META: Record<string, { repoDir: string; packageName: string }> = {
@@ -39,17 +40,12 @@ async function saveMeta(circuitsDir: string, meta: ArtifactsMeta): Promise<void>
   await fs.writeFile(metaPath, JSON.stringify(meta, null, 2));
 }
 
-async function fetchRemoteHash(url: string, type: 'text' | 'binary'): Promise<string> {
+async function fetchRemoteHash(url: string): Promise<string> {
   const response = await fetch(url);
   if (!response.ok) {
     throw new Error(`Failed to fetch ${url}: ${response.status}`);
   }
-  if (type === 'binary') {
-    const buffer = Buffer.from(await response.arrayBuffer());
-    return sha256(buffer);
-  }
-  const content = await response.text();
-  return sha256(content);
+  return sha256(await readBodyCapped(response, url, getMaxArtifactBytes()));
 }
 
 /**
@@ -66,11 +62,11 @@ export async function downloadArtifacts(circuitsDir: string, repoBaseUrl: string
 
     const jsonUrl = `${repoBaseUrl}/${cirMeta.repoDir}/target/${cirMeta.packageName}.json`;
     const jsonPath = path.join(targetDir, `${cirMeta.packageName}.json`);
-    await downloadFile(jsonUrl, jsonPath, 'text');
+    await downloadFile(jsonUrl, jsonPath);
 
     const vkUrl = `${repoBaseUrl}/${cirMeta.repoDir}/target/vk/vk`;
     const vkPath = path.join(vkDir, 'vk');
-    await downloadFile(vkUrl, vkPath, 'binary');
+    await downloadFile(vkUrl, vkPath);
 
     const jsonContent = await fs.readFile(jsonPath);
     const vkContent = await fs.readFile(vkPath);
@@ -117,7 +113,6 @@ export async function ensureArtifacts(circuitsDir: string, repoBaseUrl?: string)
     try {
       const remoteJsonHash = await fetchRemoteHash(
         `${baseUrl}/${cirMeta.repoDir}/target/${cirMeta.packageName}.json`,
-        'text',
       );
       if (remoteJsonHash !== stored.jsonHash) {
         toDownload.push(circuitId);
@@ -141,19 +136,44 @@ export function getCircuitArtifactDir(circuitsDir: string, circuitId: string): s
 }
 
 /**
- * Helper function to download a file
+ * Read a response body, rejecting it once it is larger than maxBytes: up front
+ * when Content-Length announces it, otherwise as soon as the streamed bytes
+ * pass the limit, so an oversized body is never held in memory whole.
  */
-async function downloadFile(url: string, dest: string, type: 'text' | 'binary'): Promise<void> {
+async function readBodyCapped(response: Response, label: string, maxBytes: number): Promise<Buffer> {
+  const announced = response.headers.get('content-length');
+  if (announced) {
+    assertArtifactSize(label, parseInt(announced, 10), maxBytes);
+  }
+  if (!response.body) {
+    return Buffer.alloc(0);
+  }
+
+  const reader = response.body.getReader();
+  const chunks: Uint8Array[] = [];
+  let received = 0;
+  for (;;) {
+    const { done, value } = await reader.read();
+    if (done) break;
+    received += value.byteLength;
+    if (received > maxBytes) {
+      await reader.cancel();
+      throw new Error(`Circuit artifact too large: ${label} exceeds ${maxBytes} bytes`);
+    }
+    chunks.push(value);
+  }
+  return Buffer.concat(chunks);
+}
+
+/**
+ * Helper function to download a file.
+ * Rejects artifacts larger than MAX_ARTIFACT_BYTES (announced or actual size).
+ */
+async function downloadFile(url: string, dest: string): Promise<void> {
   const response = await fetch(url);
   if (!response.ok) {
     throw new Error(`Failed to download ${url}: ${response.status} ${response.statusText}`);
   }
 
-  if (type === 'binary') {
-    const buffer = Buffer.from(await response.arrayBuffer());
-    await fs.writeFile(dest, buffer);
-  } else {
-    const content = await response.text();
-    await fs.writeFile(dest, content);
-  }
+  await fs.writeFile(dest, await readBodyCapped(response, url, getMaxArtifactBytes()));
 }
@@ -39,6 +39,7 @@ import { GeminiProvider } from './chat/geminiClient.js';
 import { MultiLLMProvider } from './chat/multiProvider.js';
 import { syncDeployments } from './config/deployments.js';
 import { startAcpSeller } from './virtuals/acpSeller.js';
+import { ShutdownController, getShutdownGraceMs, shutdownHttpServer, trackHttpRequests } from './shutdown.js';
 
 function createApp(config: Config) {
   // Validate payment config at startup
@@ -48,6 +49,10 @@ function createApp(config: Config) {
 
   const app = express();
 
+  // Graceful shutdown: in-flight requests are counted; once draining, everything (incl. /health) gets 503
+  const shutdown = new ShutdownController();
+  app.use(trackHttpRequests(shutdown));
+
   // Build swagger spec with dynamic base URL
   const swaggerSpec = buildSwaggerSpec(config.a2aBaseUrl);
 
@@ -208,7 +213,7 @@ function createApp(config: Config) {
     res.status(405).json({ error: 'Session management not supported in stateless mode.' });
   });
 
-  return { app, teeProvider, cleanupWorker, tokenIdRef };
+  return { app, teeProvider, cleanupWorker, tokenIdRef, shutdown };
 }
 
 async function startServer() {
@@ -234,9 +239,9 @@ async function startServer() {
     const earlyTeeProvider = createTeeProvider({ ...teeConfig, mode: resolvedTeeMode });
 
     // Create app without tokenId (registration runs in background after server starts)
-    const { app, teeProvider, cleanupWorker, tokenIdRef } = createApp(config);
+    const { app, teeProvider, cleanupWorker, tokenIdRef, shutdown } = createApp(config);
 
-    app.listen(config.port, () => {
+    const server = app.listen(config.port, () => {
       log.info({ action: 'server.started', port: config.port }, 'proofport-ai server listening');
       log.info({ action: 'server.mcp.ready', mcpEndpoint: `http://localhost:${config.port}/mcp` }, 'MCP endpoint ready');
       log.info({ action: 'server.config', nodeEnv: config.nodeEnv, paymentMode: paymentModeConfig.mode, paymentDescription: paymentModeConfig.description }, 'Server configuration');
@@ -267,6 +272,32 @@ async function startServer() {
           log.warn({ action: 'server.identity.failed', err }, 'ERC-8004 identity registration failed (non-fatal)');
         });
     });
+
+    // SIGTERM (Kubernetes, ECS) or ctrl-c: stop accepting connections, let in-flight
+    // proofs finish within SHUTDOWN_GRACE_SECS, then exit. A second signal exits immediately.
+    const graceMs = getShutdownGraceMs();
+    const stop = async (signal: string) => {
+      if (shutdown.draining) {
+        log.warn({ action: 'server.shutdown.forced', signal }, 'Second signal during shutdown; exiting now');
+        process.exit(1);
+      }
+      log.info(
+        { action: 'server.shutdown.started', signal, activeRequests: shutdown.activeRequests, graceMs },
+        'Shutting down; draining in-flight requests',
+      );
+      cleanupWorker.stop();
+      const drained = await shutdownHttpServer(server, shutdown, graceMs);
+      if (!drained) {
+        log.warn(
+          { action: 'server.shutdown.drain_timeout', activeRequests: shutdown.activeRequests },
+          'Shutdown grace period elapsed with requests in flight',
+        );
+      }
+      log.info({ action: 'server.shutdown.completed', drained }, 'Server stopped');
+      process.exit(drained ? 0 : 1);
+    };
+    process.on('SIGTERM', () => void stop('SIGTERM'));
+    process.on('SIGINT', () => void stop('SIGINT'));
   } catch (error) {
     log.error({ action: 'server.start.failed', err: error }, 'Failed to start server');
     process.exit(1);
@@ -24,9 +24,13 @@ const COINBASE_COUNTRY_ATTESTATION_INPUT_LENGTH = 921;
 
 /**
  * Convert a hex string to an array of byte values (numbers 0-255).
+ * Throws on odd-length or non-hex input instead of producing wrong bytes.
  */
 export function hexToBytes(hex: string): number[] {
   const cleanHex = hex.startsWith('0x') ? hex.slice(2) : hex;
+  if (cleanHex.length % 2 !== 0 || !/^[0-9a-fA-F]*$/.test(cleanHex)) {
+    throw new Error(`Invalid hex string (${cleanHex.length} characters): expected an even number of hex digits`);
+  }
   const bytes: number[] = [];
   for (let i = 0; i < cleanHex.length; i += 2) {
     bytes.push(parseInt(cleanHex.slice(i, i + 2), 16));
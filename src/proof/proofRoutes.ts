@@ -1,7 +1,7 @@
 import { Router, type Request, type Response } from 'express';
 import type { RedisClient } from '../redis/client.js';
 import type { Config } from '../config/index.js';
-import type { TeeProvider } from '../tee/types.js';
+import type { TeeProvider, VsockResponse } from '../tee/types.js';
 import type { CircuitId } from '../config/circuits.js';
 import { verifyPaymentOnChain } from './paymentVerifier.js';
 import { BbProver } from '../prover/bbProver.js';
@@ -37,11 +37,45 @@ export interface ProofRoutesDeps {
   teeProvider?: TeeProvider;
 }
 
+/** Lifetime of an x402 payment nonce in Redis */
+const NONCE_TTL_SECONDS = 300;
+
+/** Seconds a client should wait before resending after a retryable TEE error */
+const TEE_RETRY_AFTER_SECONDS = 5;
+
+/**
+ * Answer a retryable TEE error (timeouts, transient NSM failures, draining) with
+ * 503 and Retry-After, so clients can tell them from permanent failures.
+ *
+ * The payment nonce was consumed before proving, so it is stored again first:
+ * the client resends with the same X-Payment-TX / X-Payment-Nonce and is not
+ * asked to pay twice.
+ * @returns false when the error is not retryable and nothing was sent
+ */
+async function sendRetryableTeeError(
+  res: Response,
+  vsockResponse: VsockResponse,
+  redis: RedisClient,
+  paymentNonce: string,
+  circuitId: CircuitId,
+): Promise<boolean> {
+  if (!vsockResponse.retryable) return false;
+  await redis.set(`x402:nonce:${paymentNonce}`, circuitId, 'EX', NONCE_TTL_SECONDS);
+  res.setHeader('Retry-After', String(TEE_RETRY_AFTER_SECONDS));
+  res.status(503).json({
+    error: vsockResponse.code || 'TEE_UNAVAILABLE',
+    message: vsockResponse.error || 'TEE is temporarily unavailable',
+    retryable: true,
+  });
+  return true;
+}
+
 /** Shared context for proof generation */
 interface ProveContext {
   circuitId: CircuitId;
   inputs: NonNullable<ProveRequest['inputs']>;
   paymentTxHash: string;
+  paymentNonce: string;
   paymentVerifyMs: number;
   startTime: number;
   requestId: string;
@@ -143,6 +177,7 @@ async function generateProofFromInputs(
     );
 
     if (vsockResponse.type === 'error') {
+      if (await sendRetryableTeeError(res, vsockResponse, deps.redis, ctx.paymentNonce, circuitId)) return;
       throw new Error(`TEE proof generation failed: ${vsockResponse.error}`);
     }
 
@@ -274,7 +309,7 @@ export function createProofRoutes(deps: ProofRoutesDeps): Router {
         const nonce = ethers.hexlify(ethers.randomBytes(32));
 
         // Store nonce in Redis with 5-min TTL (replay protection)
-        await deps.redis.set(`x402:nonce:${nonce}`, circuitId, 'EX', 300);
+        await deps.redis.set(`x402:nonce:${nonce}`, circuitId, 'EX', NONCE_TTL_SECONDS);
 
         const isDisabled = config.paymentMode === 'disabled';
         const paymentRequirements = {
@@ -404,10 +439,12 @@ export function createProofRoutes(deps: ProofRoutesDeps): Router {
 
         if (vsockResponse.type === 'error') {
           // Handle key rotation
-          if (vsockResponse.error?.includes('Key ID mismatch')) {
+          // Enclaves built before error codes only report the message
+          if (vsockResponse.code === 'KEY_ROTATED' || vsockResponse.error?.includes('Key ID mismatch')) {
             res.status(409).json({ error: 'KEY_ROTATED', message: 'TEE key has rotated. Fetch new public key from GET /api/v1/tee/public-key and re-encrypt.' });
             return;
           }
+          if (await sendRetryableTeeError(res, vsockResponse, deps.redis, paymentNonceHeader, circuitId)) return;
           throw new Error(`TEE proof generation failed: ${vsockResponse.error}`);
         }
 
@@ -481,6 +518,7 @@ export function createProofRoutes(deps: ProofRoutesDeps): Router {
           // eslint-disable-next-line @typescript-eslint/no-non-null-assertion
           inputs: body.inputs!, // guarded above: non-encrypted path requires inputs
           paymentTxHash: paymentTxHeader,
+          paymentNonce: paymentNonceHeader,
           paymentVerifyMs,
           startTime,
           requestId,
@@ -8,6 +8,7 @@ import { Noir } from '@noir-lang/noir_js';
 import type { CircuitParams } from '../input/inputBuilder.js';
 import type { OidcCircuitInputs } from './inputFormatter.js';
 import { formatCoinbaseInputs, formatOidcInputs } from './inputFormatter.js';
+import { readCircuitJson, getMaxArtifactBytes } from '../circuit/artifactGuard.js';
 import { createLogger } from '../logger.js';
 
 const log = createLogger('Prover');
@@ -62,9 +63,9 @@ export class BbProver {
     await fs.mkdir(proofDir, { recursive: true });
 
     try {
-      // 1. Load compiled circuit JSON
+      // 1. Load compiled circuit JSON (size-checked before reading, bytecode validated)
       const circuitJsonPath = path.join(this.config.circuitsDir, meta.dir, 'target', `${meta.packageName}.json`);
-      const circuitJson = JSON.parse(await fs.readFile(circuitJsonPath, 'utf-8'));
+      const circuitJson = await readCircuitJson(circuitJsonPath, getMaxArtifactBytes());
 
       // 2. Build noir_js-compatible inputs
       let noirInputs: Record<string, unknown>;
@@ -0,0 +1,99 @@
+/**
+ * Graceful shutdown shared by the HTTP server (src/index.ts) and the enclave
+ * server (src/aws/enclave-server.ts): on SIGTERM/SIGINT the listener is
+ * closed, new work is refused, and in-flight requests get up to
+ * SHUTDOWN_GRACE_SECS to finish before the process exits.
+ */
+
+import type { Server } from 'node:http';
+import type { RequestHandler } from 'express';
+
+export const DEFAULT_SHUTDOWN_GRACE_MS = 120_000;
+
+export function getShutdownGraceMs(env: NodeJS.ProcessEnv = process.env): number {
+  const seconds = parseFloat(env.SHUTDOWN_GRACE_SECS ?? '');
+  return Number.isFinite(seconds) && seconds >= 0 ? seconds * 1000 : DEFAULT_SHUTDOWN_GRACE_MS;
+}
+
+/** Counts in-flight requests so shutdown can wait for them */
+export class ShutdownController {
+  private active = 0;
+  private isDraining = false;
+  private idleWaiters: Array<() => void> = [];
+
+  /** True once drain() has been called; health reports it so the parent stops routing */
+  get draining(): boolean {
+    return this.isDraining;
+  }
+
+  get activeRequests(): number {
+    return this.active;
+  }
+
+  /** Run one request, counting it as in flight until it settles */
+  async track<T>(task: () => Promise<T>): Promise<T> {
+    this.active += 1;
+    try {
+      return await task();
+    } finally {
+      this.active -= 1;
+      if (this.active === 0) {
+        const waiters = this.idleWaiters;
+        this.idleWaiters = [];
+        waiters.forEach((resolve) => resolve());
+      }
+    }
+  }
+
+  /**
+   * Start draining and wait for in-flight requests.
+   * @returns true once idle, false if graceMs elapsed first
+   */
+  drain(graceMs: number): Promise<boolean> {
+    this.isDraining = true;
+    if (this.active === 0) return Promise.resolve(true);
+
+    return new Promise((resolve) => {
+      const timer = setTimeout(() => resolve(false), graceMs);
+      this.idleWaiters.push(() => {
+        clearTimeout(timer);
+        resolve(true);
+      });
+    });
+  }
+}
+
+/**
+ * Express middleware counting each request as in flight until its response
+ * closes. Once draining, every request (including /health, so the load
+ * balancer stops routing here) is answered 503 on keep-alive connections.
+ */
+export function trackHttpRequests(shutdown: ShutdownController): RequestHandler {
+  return (_req, res, next) => {
+    if (shutdown.draining) {
+      res.set('Connection', 'close');
+      res.status(503).json({ status: 'draining', error: 'Server is shutting down' });
+      return;
+    }
+    void shutdown.track(() => new Promise<void>((resolve) => res.on('close', resolve)));
+    next();
+  };
+}
+
+/**
+ * Stop accepting connections and wait for in-flight requests, closing
+ * whatever is still open once graceMs has elapsed. Called by the signal
+ * handler; tests call it directly.
+ * @returns true if every request finished within graceMs
+ */
+export async function shutdownHttpServer(
+  server: Server,
+  shutdown: ShutdownController,
+  graceMs: number,
+): Promise<boolean> {
+  server.close();
+  server.closeIdleConnections();
+  const drained = await shutdown.drain(graceMs);
+  if (!drained) server.closeAllConnections();
+  return drained;
+}
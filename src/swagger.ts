@@ -57,6 +57,20 @@ export function buildSwaggerSpec(baseUrl: string) {
               },
             },
           },
+          '503': {
+            description: 'Server is shutting down and draining in-flight requests',
+            content: {
+              'application/json': {
+                schema: {
+                  type: 'object',
+                  properties: {
+                    status: { type: 'string', example: 'draining' },
+                    error: { type: 'string', example: 'Server is shutting down' },
+                  },
+                },
+              },
+            },
+          },
         },
       },
     },
@@ -685,6 +699,10 @@ export function buildSwaggerSpec(baseUrl: string) {
           },
           '402': { description: 'Payment invalid (transaction not found, wrong amount, or wrong recipient)' },
           '404': { description: 'Session not found or expired' },
+          '503': {
+            description: 'Retryable TEE error (timeout, transient attestation failure, or draining); no proof was generated. Retry after Retry-After seconds with the same X-Payment-TX and X-Payment-Nonce; the nonce is kept.',
+            headers: { 'Retry-After': { schema: { type: 'integer', example: 5 } } },
+          },
         },
       },
     },
@@ -66,6 +66,11 @@ export class EnclaveClient implements TeeProvider {
       };
 
       const response = await this.sendVsockRequest(request);
+      if (response.type === 'health' && response.status === 'draining') {
+        // Still answering, but refusing new proofs until it exits
+        log.info({ action: 'enclave.health.draining' }, 'Enclave is shutting down');
+        return false;
+      }
       return response.type === 'health';
     } catch (error) {
       log.error({ action: 'enclave.health.failed', err: error }, 'Health check failed');
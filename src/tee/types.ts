@@ -36,27 +36,60 @@ export interface EnclaveImageConfig {
 
 /**
  * Request sent to enclave via vsock
+ *
+ * Encrypted prove: `circuitId`/`inputs` are replaced by `encryptedPayload`, an
+ * EncryptedEnvelope of hex fields { ephemeralPublicKey (32 B X25519), iv (12 B),
+ * ciphertext, authTag (16 B), keyId }. The plaintext is the JSON
+ * `{ circuitId, inputs }`, sealed with AES-256-GCM under SHA-256(ECDH(ephemeral,
+ * enclave key)). The enclave key comes from a getPublicKey request, whose NSM
+ * attestation carries it as public_key. A stale keyId fails with KEY_ROTATED;
+ * any other failure is DECRYPTION_FAILED with no further detail.
  */
 export interface VsockRequest {
-  type: 'prove' | 'health' | 'getPublicKey';
+  type: 'prove' | 'health' | 'attestation' | 'getPublicKey' | 'verify' | 'listCircuits' | 'errorDetail';
   circuitId?: string;
   inputs?: Record<string, any>; // Structured circuit inputs (coinbase: CircuitParams-like, OIDC: OidcCircuitInputs)
   encryptedPayload?: EncryptedEnvelope; // E2E encrypted payload for TEE
   requestId: string;
+  proofHash?: string; // attestation requests: hex hash bound into NSM user_data
+  nonce?: string; // attestation requests: hex nonce bound into the NSM document (max 512 bytes)
+  proof?: string; // verify requests: hex proof
+  publicInputs?: string[]; // verify requests: hex public inputs, as returned in proof responses
+  disableZk?: boolean; // verify requests: the proof was generated with disableZk (see proveSettings)
+  errorId?: string; // errorDetail requests: errorId from a redacted error response
+  metadata?: Record<string, unknown>; // prove options, validated by the enclave (see src/aws/enclaveProtocol.ts)
+}
+
+/** A circuit directory in the enclave image; `missing` lists absent artifacts when incomplete */
+export interface EnclaveCircuitListing {
+  circuitId: string;
+  dir: string;
+  status: 'complete' | 'incomplete';
+  missing: string[];
 }
 
 /**
  * Response received from enclave via vsock
  */
 export interface VsockResponse {
-  type: 'proof' | 'health' | 'error' | 'publicKey';
+  type: 'proof' | 'health' | 'attestation' | 'error' | 'publicKey' | 'verify' | 'circuits' | 'errorDetail';
   requestId: string;
   proof?: string;
   publicInputs?: string[];
+  status?: 'ok' | 'draining'; // health responses: 'draining' once the enclave is shutting down
+  isValid?: boolean;   // verify responses: whether bb verify accepted the proof
+  circuits?: EnclaveCircuitListing[]; // listCircuits responses: every circuit directory in the image
   attestationDocument?: string; // base64-encoded COSE Sign1
   error?: string;
-  publicKey?: string;  // hex-encoded X25519 public key
+  code?: string;       // machine-readable error code (error responses only)
+  retryable?: boolean; // whether resending the same request is safe (error responses only)
+  errorId?: string;    // error responses: key for the full detail (errorDetail request); errorDetail: the id looked up
+  detail?: { errorId: string; requestId: string; code: string; error: string; at: string } | null; // errorDetail responses; null once aged out
+  publicKey?: string;  // hex; X25519 encryption key (publicKey responses) or Ed25519 signing key (attestation responses)
   keyId?: string;      // key rotation identifier
+  tenant?: string;     // echoed from prove metadata.tenant
+  nonce?: string;      // attestation responses: the nonce bound into the document (lowercase hex)
+  proveSettings?: { lowMemory: boolean; disableZk: boolean }; // bb settings the proof was generated with
 }
 
 /**
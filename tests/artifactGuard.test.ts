@@ -0,0 +1,92 @@
+import { describe, it, expect, beforeEach, afterEach } from 'vitest';
+import * as fs from 'node:fs/promises';
+import * as path from 'node:path';
+import * as os from 'node:os';
+import * as crypto from 'node:crypto';
+import {
+  DEFAULT_MAX_ARTIFACT_BYTES,
+  getMaxArtifactBytes,
+  assertArtifactSize,
+  validateBytecodeField,
+  readCircuitJson,
+} from '../src/circuit/artifactGuard.js';
+
+describe('artifactGuard', () => {
+  let testDir: string;
+
+  beforeEach(async () => {
+    testDir = path.join(os.tmpdir(), `artifact-guard-${crypto.randomUUID()}`);
+    await fs.mkdir(testDir, { recursive: true });
+  });
+
+  afterEach(async () => {
+    await fs.rm(testDir, { recursive: true, force: true }).catch(() => {});
+  });
+
+  describe('getMaxArtifactBytes', () => {
+    it('defaults to 64MB', () => {
+      expect(getMaxArtifactBytes({})).toBe(DEFAULT_MAX_ARTIFACT_BYTES);
+      expect(DEFAULT_MAX_ARTIFACT_BYTES).toBe(64 * 1024 * 1024);
+    });
+
+    it('honours MAX_ARTIFACT_BYTES and ignores invalid values', () => {
+      expect(getMaxArtifactBytes({ MAX_ARTIFACT_BYTES: '1024' })).toBe(1024);
+      expect(getMaxArtifactBytes({ MAX_ARTIFACT_BYTES: 'abc' })).toBe(DEFAULT_MAX_ARTIFACT_BYTES);
+      expect(getMaxArtifactBytes({ MAX_ARTIFACT_BYTES: '0' })).toBe(DEFAULT_MAX_ARTIFACT_BYTES);
+    });
+  });
+
+  describe('assertArtifactSize', () => {
+    it('accepts sizes up to the limit and names the artifact when over', () => {
+      expect(() => assertArtifactSize('a.json', 10, 10)).not.toThrow();
+      expect(() => assertArtifactSize('a.json', 11, 10)).toThrow(
+        'Circuit artifact too large: a.json is 11 bytes (limit 10)',
+      );
+    });
+  });
+
+  describe('validateBytecodeField', () => {
+    it('accepts base64 bytecode', () => {
+      expect(() => validateBytecodeField({ bytecode: 'H4sIAAAA' }, 'c.json', 1024)).not.toThrow();
+    });
+
+    it('rejects missing, non-base64 and oversized bytecode', () => {
+      expect(() => validateBytecodeField({}, 'c.json', 1024)).toThrow('has no bytecode field');
+      expect(() => validateBytecodeField(null, 'c.json', 1024)).toThrow('has no bytecode field');
+      expect(() => validateBytecodeField({ bytecode: 'not base64!' }, 'c.json', 1024)).toThrow('not valid base64');
+      expect(() => validateBytecodeField({ bytecode: 'abc' }, 'c.json', 1024)).toThrow('not valid base64');
+      expect(() => validateBytecodeField({ bytecode: 'AAAAAAAA' }, 'c.json', 4)).toThrow('8 chars (limit 4)');
+    });
+  });
+
+  describe('readCircuitJson', () => {
+    it('reads a valid circuit JSON', async () => {
+      const file = path.join(testDir, 'ok.json');
+      await fs.writeFile(file, JSON.stringify({ bytecode: 'H4sIAAAA', abi: {} }));
+
+      const json = await readCircuitJson(file, 1024);
+      expect(json.bytecode).toBe('H4sIAAAA');
+    });
+
+    it('rejects oversized files before reading them', async () => {
+      const file = path.join(testDir, 'big.json');
+      await fs.writeFile(file, JSON.stringify({ bytecode: 'A'.repeat(4096) }));
+
+      await expect(readCircuitJson(file, 1024)).rejects.toThrow('Circuit artifact too large');
+    });
+
+    it('rejects malformed JSON with a clear error', async () => {
+      const file = path.join(testDir, 'broken.json');
+      await fs.writeFile(file, '{"bytecode": "H4sI');
+
+      await expect(readCircuitJson(file, 1024)).rejects.toThrow('is not valid JSON');
+    });
+
+    it('rejects JSON without plausible bytecode', async () => {
+      const file = path.join(testDir, 'nobytecode.json');
+      await fs.writeFile(file, JSON.stringify({ abi: {} }));
+
+      await expect(readCircuitJson(file, 1024)).rejects.toThrow('has no bytecode field');
+    });
+  });
+});
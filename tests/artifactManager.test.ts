@@ -51,11 +51,7 @@ describe('artifactManager', () => {
 
     it('fetches correct URLs for all circuits', async () => {
       const mockFetch = vi.mocked(global.fetch);
-      mockFetch.mockResolvedValue({
-        ok: true,
-        text: async () => '{}',
-        arrayBuffer: async () => new ArrayBuffer(0),
-      } as Response);
+      mockFetch.mockImplementation(async () => new Response('{}'));
 
       await downloadArtifacts(circuitsDir, repoBaseUrl);
 
@@ -73,11 +69,7 @@ describe('artifactManager', () => {
 
     it('creates correct directory structure', async () => {
       const mockFetch = vi.mocked(global.fetch);
-      mockFetch.mockResolvedValue({
-        ok: true,
-        text: async () => '{"test": true}',
-        arrayBuffer: async () => new ArrayBuffer(8),
-      } as Response);
+      mockFetch.mockImplementation(async () => new Response('{"test": true}'));
 
       await downloadArtifacts(circuitsDir, repoBaseUrl);
 
@@ -107,6 +99,56 @@ describe('artifactManager', () => {
 
       await expect(downloadArtifacts(circuitsDir, repoBaseUrl)).rejects.toThrow();
     });
+
+    it('rejects artifacts whose announced size exceeds the limit', async () => {
+      const mockFetch = vi.mocked(global.fetch);
+      mockFetch.mockImplementation(async () => new Response('{}', {
+        headers: { 'content-length': String(10 * 1024 * 1024 * 1024) },
+      }));
+
+      await expect(downloadArtifacts(circuitsDir, repoBaseUrl)).rejects.toThrow('Circuit artifact too large');
+    });
+
+    it('rejects downloaded bodies larger than MAX_ARTIFACT_BYTES', async () => {
+      const previous = process.env.MAX_ARTIFACT_BYTES;
+      process.env.MAX_ARTIFACT_BYTES = '16';
+      try {
+        const mockFetch = vi.mocked(global.fetch);
+        mockFetch.mockImplementation(async () => new Response(JSON.stringify({ bytecode: 'A'.repeat(64) })));
+
+        await expect(downloadArtifacts(circuitsDir, repoBaseUrl)).rejects.toThrow('Circuit artifact too large');
+      } finally {
+        if (previous === undefined) delete process.env.MAX_ARTIFACT_BYTES;
+        else process.env.MAX_ARTIFACT_BYTES = previous;
+      }
+    });
+
+    it('stops reading a body without Content-Length once it passes the limit', async () => {
+      const previous = process.env.MAX_ARTIFACT_BYTES;
+      process.env.MAX_ARTIFACT_BYTES = String(64 * 1024);
+      try {
+        // An endless body with no Content-Length: only the streamed byte count can stop it
+        let pulled = 0;
+        const mockFetch = vi.mocked(global.fetch);
+        mockFetch.mockImplementation(async () => new Response(new ReadableStream({
+          pull(controller) {
+            pulled += 1;
+            controller.enqueue(new Uint8Array(16 * 1024));
+          },
+        })));
+
+        await expect(downloadArtifacts(circuitsDir, repoBaseUrl)).rejects.toThrow(
+          'Circuit artifact too large',
+        );
+        expect(pulled).toBeLessThan(10);
+        await expect(fs.access(
+          path.join(circuitsDir, 'coinbase-attestation', 'target', 'coinbase_attestation.json'),
+        )).rejects.toThrow();
+      } finally {
+        if (previous === undefined) delete process.env.MAX_ARTIFACT_BYTES;
+        else process.env.MAX_ARTIFACT_BYTES = previous;
+      }
+    });
   });
 
   describe('ensureArtifacts', () => {
@@ -122,11 +164,7 @@ describe('artifactManager', () => {
 
     it('downloads if artifacts do not exist', async () => {
       const mockFetch = vi.mocked(global.fetch);
-      mockFetch.mockResolvedValue({
-        ok: true,
-        text: async () => '{}',
-        arrayBuffer: async () => new ArrayBuffer(0),
-      } as Response);
+      mockFetch.mockImplementation(async () => new Response('{}'));
 
       await ensureArtifacts(circuitsDir, repoBaseUrl);
 
@@ -165,11 +203,7 @@ describe('artifactManager', () => {
 
       const mockFetch = vi.mocked(global.fetch);
       // Remote hash check returns same content → hashes match → no re-download
-      mockFetch.mockResolvedValue({
-        ok: true,
-        text: async () => jsonContent,
-        arrayBuffer: async () => new ArrayBuffer(0),
-      } as Response);
+      mockFetch.mockImplementation(async () => new Response(jsonContent));
 
       await ensureArtifacts(circuitsDir, repoBaseUrl);
 
@@ -0,0 +1,82 @@
+import { describe, it, expect, vi } from 'vitest';
+import { handleAttestation } from '../../src/aws/attestation.js';
+import type { AttestationDeps } from '../../src/aws/attestation.js';
+import { generateSigningKey } from '../../src/aws/enclaveKeys.js';
+
+const DOC = Buffer.from('attestation-document');
+
+function deps(overrides: Partial<AttestationDeps> = {}): AttestationDeps {
+  return {
+    attest: vi.fn(async () => DOC),
+    nsmAvailable: () => true,
+    signingKey: generateSigningKey(),
+    ...overrides,
+  };
+}
+
+describe('handleAttestation()', () => {
+  it('should attest without a nonce and still bind the signing key', async () => {
+    const d = deps();
+    const response = await handleAttestation({ type: 'attestation', requestId: 'r1', proofHash: '0xabcd' }, d);
+
+    expect(d.attest).toHaveBeenCalledWith(Buffer.from('abcd', 'hex'), d.signingKey!.publicKeyRaw, undefined);
+    expect(response).toEqual({
+      type: 'attestation',
+      requestId: 'r1',
+      attestationDocument: DOC.toString('base64'),
+      publicKey: d.signingKey!.publicKeyRaw.toString('hex'),
+    });
+  });
+
+  it('should pass the nonce to NSM and echo it', async () => {
+    const d = deps();
+    const response = await handleAttestation(
+      { type: 'attestation', requestId: 'r2', proofHash: 'abcd', nonce: '0xFF01' },
+      d,
+    );
+
+    expect(d.attest).toHaveBeenCalledWith(Buffer.from('abcd', 'hex'), d.signingKey!.publicKeyRaw, Buffer.from('ff01', 'hex'));
+    expect(response).toMatchObject({ type: 'attestation', requestId: 'r2', nonce: 'ff01', publicKey: d.signingKey!.publicKeyRaw.toString('hex') });
+  });
+
+  it('should omit the signing key before it is initialized', async () => {
+    const d = deps({ signingKey: null });
+    const response = await handleAttestation({ type: 'attestation', requestId: 'r3' }, d);
+
+    expect(d.attest).toHaveBeenCalledWith(Buffer.alloc(0), undefined, undefined);
+    expect(response).not.toHaveProperty('publicKey');
+  });
+
+  it('should reject a proofHash that is not hex before calling NSM', async () => {
+    const d = deps();
+    const response = await handleAttestation(
+      { type: 'attestation', requestId: 'r4', proofHash: '--user-data' },
+      d,
+    );
+
+    expect(response).toMatchObject({ type: 'error', code: 'BAD_REQUEST', retryable: false });
+    expect(d.attest).not.toHaveBeenCalled();
+  });
+
+  it('should reject a malformed nonce', async () => {
+    const d = deps();
+    const response = await handleAttestation({ type: 'attestation', requestId: 'r5', nonce: 'xyz' }, d);
+
+    expect(response).toMatchObject({ type: 'error', code: 'BAD_REQUEST' });
+    expect(d.attest).not.toHaveBeenCalled();
+  });
+
+  it('should tell a failed attestation from a missing NSM device', async () => {
+    const failed = await handleAttestation(
+      { type: 'attestation', requestId: 'r6' },
+      deps({ attest: async () => null }),
+    );
+    expect(failed).toMatchObject({ code: 'ATTESTATION_FAILED', retryable: true });
+
+    const unavailable = await handleAttestation(
+      { type: 'attestation', requestId: 'r7' },
+      deps({ attest: async () => null, nsmAvailable: () => false }),
+    );
+    expect(unavailable).toMatchObject({ code: 'ATTESTATION_UNAVAILABLE' });
+  });
+});
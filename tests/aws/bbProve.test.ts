@@ -0,0 +1,84 @@
+import { describe, it, expect, beforeEach, afterEach } from 'vitest';
+import * as fs from 'node:fs/promises';
+import * as path from 'node:path';
+import * as os from 'node:os';
+import * as crypto from 'node:crypto';
+import { runBbProve } from '../../src/aws/bbProve.js';
+import type { BbProveRequest, BbProveDeps } from '../../src/aws/bbProve.js';
+import { EnclaveError } from '../../src/aws/enclaveProtocol.js';
+import { fakeBb } from './fixtures/fakeBb.js';
+
+describe('runBbProve()', () => {
+  let testDir: string;
+  let base: string;
+  let request: BbProveRequest;
+  let deps: BbProveDeps;
+  let argv: string[][];
+
+  beforeEach(async () => {
+    testDir = path.join(os.tmpdir(), `bbprove-test-${crypto.randomUUID()}`);
+    base = path.join(testDir, 'work');
+    await fs.mkdir(base, { recursive: true });
+    await fs.writeFile(path.join(testDir, 'circuit.json'), '{"bytecode":"H4sI"}');
+    await fs.writeFile(path.join(testDir, 'vk'), Buffer.alloc(16));
+    argv = [];
+    request = {
+      requestId: 'req-1',
+      bytecode: path.join(testDir, 'circuit.json'),
+      vk: path.join(testDir, 'vk'),
+      witness: Buffer.from('w'),
+      settings: { lowMemory: false, disableZk: false },
+      timeoutMs: 10_000,
+      verify: false,
+      checkArtifacts: () => null,
+    };
+    deps = {
+      workBase: base,
+      runBb: (label, args, options) => {
+        argv.push(args);
+        return fakeBb(label, args, options);
+      },
+      stallWindowMs: 0,
+      verifyTimeoutMs: 10_000,
+      keepFailedWorkDir: false,
+      log: () => {},
+    };
+  });
+
+  afterEach(async () => {
+    await fs.rm(testDir, { recursive: true, force: true }).catch(() => {});
+  });
+
+  it('should return the proof and public inputs and remove the work dir', async () => {
+    const result = await runBbProve(request, deps);
+    expect(result.proof).toEqual(Buffer.alloc(64, 1));
+    expect(result.publicInputs).toEqual(Buffer.alloc(32, 2));
+    expect(await fs.readdir(base)).toEqual([]);
+  });
+
+  it('should pass the prove settings to bb', async () => {
+    await runBbProve({ ...request, settings: { lowMemory: true, disableZk: true } }, deps);
+    expect(argv[0]).toContain('--disable_zk');
+    expect(argv[0]).toContain('--slow_low_memory');
+  });
+
+  it('should fail with PROVE_FAILED when bb writes no proof', async () => {
+    const env = { ...process.env, FAKE_BB_PROOF_BYTES: '0' };
+    const err = await runBbProve(request, { ...deps, env }).catch((e) => e);
+    expect(err).toBeInstanceOf(EnclaveError);
+    expect(err.code).toBe('PROVE_FAILED');
+    expect(await fs.readdir(base)).toEqual([]);
+  });
+
+  it('should keep the work dir of an invalid artifact only when asked', async () => {
+    const invalid = { ...request, checkArtifacts: () => 'proof is 64 bytes' };
+
+    await expect(runBbProve(invalid, deps)).rejects.toMatchObject({ code: 'ARTIFACT_INVALID' });
+    expect(await fs.readdir(base)).toEqual([]);
+
+    await expect(runBbProve(invalid, { ...deps, keepFailedWorkDir: true })).rejects.toMatchObject({ code: 'ARTIFACT_INVALID' });
+    const kept = await fs.readdir(base);
+    expect(kept).toHaveLength(1);
+    expect((await fs.readdir(path.join(base, kept[0], 'proof'))).sort()).toEqual(['proof', 'public_inputs']);
+  });
+});
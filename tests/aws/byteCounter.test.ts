@@ -0,0 +1,70 @@
+import { describe, it, expect } from 'vitest';
+import { ByteCounter, KeyedByteCounter, OTHER_KEY, saturatingAdd } from '../../src/aws/byteCounter.js';
+
+describe('byteCounter', () => {
+  describe('saturatingAdd()', () => {
+    it('should add byte counts', () => {
+      expect(saturatingAdd(10, 5)).toBe(15);
+    });
+
+    it('should saturate at Number.MAX_SAFE_INTEGER', () => {
+      expect(saturatingAdd(Number.MAX_SAFE_INTEGER - 1, 10)).toBe(Number.MAX_SAFE_INTEGER);
+      expect(saturatingAdd(Number.MAX_SAFE_INTEGER, Number.MAX_SAFE_INTEGER)).toBe(Number.MAX_SAFE_INTEGER);
+    });
+
+    it('should ignore negative and non-finite counts', () => {
+      expect(saturatingAdd(10, -5)).toBe(10);
+      expect(saturatingAdd(10, NaN)).toBe(10);
+      expect(saturatingAdd(10, Infinity)).toBe(10);
+    });
+  });
+
+  describe('ByteCounter', () => {
+    it('should count received and sent bytes separately', () => {
+      const counter = new ByteCounter();
+      counter.countReceived(100);
+      counter.countReceived(20);
+      counter.countSent(7);
+      expect(counter.received).toBe(120);
+      expect(counter.sent).toBe(7);
+    });
+
+    it('should fold other counters into an aggregate', () => {
+      const total = new ByteCounter();
+      for (const [received, sent] of [[10, 1], [20, 2]]) {
+        const connection = new ByteCounter();
+        connection.countReceived(received);
+        connection.countSent(sent);
+        total.add(connection);
+      }
+      expect(total.received).toBe(30);
+      expect(total.sent).toBe(3);
+    });
+  });
+
+  describe('KeyedByteCounter', () => {
+    it('should keep separate totals per key', () => {
+      const byType = new KeyedByteCounter();
+      byType.get('prove').countReceived(1000);
+      byType.get('prove').countSent(500);
+      byType.get('health').countReceived(40);
+      expect(byType.toJSON()).toEqual({
+        prove: { received: 1000, sent: 500 },
+        health: { received: 40, sent: 0 },
+      });
+    });
+
+    it('should fold keys past the limit into one bucket', () => {
+      const byType = new KeyedByteCounter(2);
+      for (const key of ['a', 'b', 'c', 'd']) {
+        byType.get(key).countReceived(1);
+      }
+      byType.get('a').countReceived(1);
+      expect(byType.toJSON()).toEqual({
+        a: { received: 2, sent: 0 },
+        b: { received: 1, sent: 0 },
+        [OTHER_KEY]: { received: 2, sent: 0 },
+      });
+    });
+  });
+});
@@ -0,0 +1,160 @@
+import { describe, it, expect, beforeEach, afterEach } from 'vitest';
+import * as fs from 'node:fs/promises';
+import * as path from 'node:path';
+import * as os from 'node:os';
+import * as crypto from 'node:crypto';
+import { discoverCircuits, listCircuits } from '../../src/aws/circuitDiscovery.js';
+
+const MAX_BYTES = 1024 * 1024;
+
+describe('circuitDiscovery', () => {
+  let baseDir: string;
+
+  beforeEach(async () => {
+    baseDir = path.join(os.tmpdir(), `circuits-test-${crypto.randomUUID()}`);
+    await fs.mkdir(baseDir, { recursive: true });
+  });
+
+  afterEach(async () => {
+    await fs.rm(baseDir, { recursive: true, force: true }).catch(() => {});
+  });
+
+  async function addCircuit(dir: string, name: string, json: unknown = { bytecode: 'H4sIAAAA' }, withVk = true) {
+    const target = path.join(baseDir, dir, 'target');
+    await fs.mkdir(path.join(target, 'vk'), { recursive: true });
+    await fs.writeFile(path.join(target, `${name}.json`), typeof json === 'string' ? json : JSON.stringify(json));
+    if (withVk) await fs.writeFile(path.join(target, 'vk', 'vk'), 'vk');
+  }
+
+  /** Like the coinbase-libs and keccak256 packages build-enclave.sh copies next to the circuits */
+  async function addLibrary(dir: string) {
+    await fs.mkdir(path.join(baseDir, dir, 'src'), { recursive: true });
+    await fs.writeFile(
+      path.join(baseDir, dir, 'Nargo.toml'),
+      `[package]\nname = "${dir.replace(/-/g, '_')}"\ntype = "lib"\ncompiler_version = ">=1.0.0"\n`,
+    );
+    await fs.writeFile(path.join(baseDir, dir, 'src', 'lib.nr'), 'pub fn helper() {}\n');
+  }
+
+  it('should derive circuit ids from the target JSON filename', async () => {
+    await addCircuit('coinbase-attestation', 'coinbase_attestation');
+    await addCircuit('new-circuit', 'new_circuit');
+
+    const { circuits, skipped } = await discoverCircuits(baseDir, MAX_BYTES);
+    expect(circuits).toEqual({
+      coinbase_attestation: { dir: 'coinbase-attestation', bytecode: 'coinbase_attestation.json', vk: 'vk/vk' },
+      new_circuit: { dir: 'new-circuit', bytecode: 'new_circuit.json', vk: 'vk/vk' },
+    });
+    expect(skipped).toEqual([]);
+  });
+
+  it('should skip directories without a target JSON or vk', async () => {
+    await fs.mkdir(path.join(baseDir, 'src-only', 'src'), { recursive: true });
+    await addCircuit('no-vk', 'no_vk', undefined, false);
+
+    const { circuits, skipped } = await discoverCircuits(baseDir, MAX_BYTES);
+    expect(circuits).toEqual({});
+    expect(skipped).toEqual([
+      { dir: 'no-vk', reason: 'missing target/vk/vk' },
+      { dir: 'src-only', reason: 'no target/<circuit>.json' },
+    ]);
+  });
+
+  it('should skip circuits whose JSON fails validation', async () => {
+    await addCircuit('broken', 'broken', '{not json');
+    await addCircuit('no-bytecode', 'no_bytecode', { abi: {} });
+    await addCircuit('good', 'good');
+
+    const { circuits, skipped } = await discoverCircuits(baseDir, MAX_BYTES);
+    expect(Object.keys(circuits)).toEqual(['good']);
+    expect(skipped.map((s) => s.dir)).toEqual(['broken', 'no-bytecode']);
+    expect(skipped[0].reason).toContain('is not valid JSON');
+    expect(skipped[1].reason).toContain('has no bytecode field');
+  });
+
+  it('should keep the first directory on duplicate ids', async () => {
+    await addCircuit('a-copy', 'shared');
+    await addCircuit('b-copy', 'shared');
+
+    const { circuits, skipped } = await discoverCircuits(baseDir, MAX_BYTES);
+    expect(circuits.shared.dir).toBe('a-copy');
+    expect(skipped).toEqual([{ dir: 'b-copy', reason: "duplicate circuit id 'shared' (already in a-copy)" }]);
+  });
+
+  it('should pick the JSON named after the directory when target has several', async () => {
+    await addCircuit('my-circuit', 'my_circuit');
+    await fs.writeFile(path.join(baseDir, 'my-circuit', 'target', 'other.json'), '{}');
+
+    const { circuits } = await discoverCircuits(baseDir, MAX_BYTES);
+    expect(Object.keys(circuits)).toEqual(['my_circuit']);
+  });
+
+  it('should reject ids that are not plain identifiers', async () => {
+    await addCircuit('weird', 'weird-name');
+
+    const { circuits, skipped } = await discoverCircuits(baseDir, MAX_BYTES);
+    expect(circuits).toEqual({});
+    expect(skipped).toEqual([{ dir: 'weird', reason: "invalid circuit id 'weird-name'" }]);
+  });
+
+  it('should ignore hidden directories and per-request work dirs', async () => {
+    await fs.mkdir(path.join(baseDir, '.probe-x'));
+    await fs.mkdir(path.join(baseDir, 'proof-req-1-abc'));
+
+    expect(await discoverCircuits(baseDir, MAX_BYTES)).toEqual({ circuits: {}, skipped: [], libraries: [] });
+  });
+
+  it('should report Noir library packages separately from skipped circuits', async () => {
+    await addCircuit('coinbase-attestation', 'coinbase_attestation');
+    await addLibrary('coinbase-libs');
+    await addLibrary('keccak256');
+
+    const { circuits, skipped, libraries } = await discoverCircuits(baseDir, MAX_BYTES);
+    expect(Object.keys(circuits)).toEqual(['coinbase_attestation']);
+    expect(skipped).toEqual([]);
+    expect(libraries).toEqual(['coinbase-libs', 'keccak256']);
+  });
+
+  it('should report an unreadable base directory', async () => {
+    const missing = path.join(baseDir, 'missing');
+    const { circuits, skipped } = await discoverCircuits(missing, MAX_BYTES);
+    expect(circuits).toEqual({});
+    expect(skipped).toEqual([{ dir: missing, reason: 'cannot read circuits directory: ENOENT' }]);
+  });
+
+  describe('listCircuits()', () => {
+    it('should report complete and incomplete circuits', async () => {
+      await addCircuit('complete-circuit', 'complete_circuit');
+      await fs.writeFile(path.join(baseDir, 'complete-circuit', 'Nargo.toml'), '[package]\n');
+      await addCircuit('no-vk', 'no_vk', undefined, false);
+      await fs.mkdir(path.join(baseDir, 'src-only', 'src'), { recursive: true });
+      await fs.mkdir(path.join(baseDir, 'proof-req-1-abc'));
+
+      expect(await listCircuits(baseDir)).toEqual([
+        { circuitId: 'complete_circuit', dir: 'complete-circuit', status: 'complete', missing: [] },
+        { circuitId: 'no_vk', dir: 'no-vk', status: 'incomplete', missing: ['target/vk/vk', 'Nargo.toml'] },
+        {
+          circuitId: 'src_only',
+          dir: 'src-only',
+          status: 'incomplete',
+          missing: ['target/src_only.json', 'target/vk/vk', 'Nargo.toml'],
+        },
+      ]);
+    });
+
+    it('should leave out Noir library packages', async () => {
+      await addCircuit('complete-circuit', 'complete_circuit');
+      await fs.writeFile(path.join(baseDir, 'complete-circuit', 'Nargo.toml'), '[package]\ntype = "bin"\n');
+      await addLibrary('coinbase-libs');
+      await addLibrary('keccak256');
+
+      expect(await listCircuits(baseDir)).toEqual([
+        { circuitId: 'complete_circuit', dir: 'complete-circuit', status: 'complete', missing: [] },
+      ]);
+    });
+
+    it('should fail when the base directory cannot be read', async () => {
+      await expect(listCircuits(path.join(baseDir, 'missing'))).rejects.toThrow('ENOENT');
+    });
+  });
+});
@@ -0,0 +1,177 @@
+import { describe, it, expect } from 'vitest';
+import { existsSync } from 'node:fs';
+import {
+  runCommand,
+  redactArgv,
+  exitedNormally,
+  CommandError,
+  StallDetector,
+  readProcessCpuTicks,
+  getStallWindowMs,
+  DEFAULT_STALL_WINDOW_MS,
+} from '../../src/aws/enclaveCommand.js';
+import type { CommandEvent } from '../../src/aws/enclaveCommand.js';
+
+const NODE = process.execPath;
+const HAS_PROC = existsSync('/proc/self/stat');
+
+describe('enclaveCommand', () => {
+  describe('redactArgv()', () => {
+    it('should replace the work dir prefix', () => {
+      expect(redactArgv(['bb', 'prove', '-w', '/app/circuits/proof-1-abc/witness.gz'], '/app/circuits/proof-1-abc'))
+        .toEqual(['bb', 'prove', '-w', '<workdir>/witness.gz']);
+    });
+
+    it('should leave argv untouched without a work dir', () => {
+      expect(redactArgv(['bb', '-k', '/app/vk'])).toEqual(['bb', '-k', '/app/vk']);
+    });
+  });
+
+  describe('StallDetector', () => {
+    it('should report a stall once CPU and output are flat for the whole window', () => {
+      const detector = new StallDetector(1000, 0);
+      expect(detector.sample(100, 5, 0)).toBe(false);
+      expect(detector.sample(600, 5, 0)).toBe(false);
+      expect(detector.sample(1099, 5, 0)).toBe(false);
+      expect(detector.sample(1100, 5, 0)).toBe(true);
+    });
+
+    it('should not flag quiet phases shorter than the window', () => {
+      const detector = new StallDetector(1000, 0);
+      detector.sample(0, 1, 0);
+      expect(detector.sample(900, 1, 0)).toBe(false);
+      expect(detector.sample(950, 2, 0)).toBe(false);
+      expect(detector.sample(1800, 2, 0)).toBe(false);
+    });
+
+    it('should treat output as progress even without CPU', () => {
+      const detector = new StallDetector(1000, 0);
+      detector.sample(0, 1, 0);
+      expect(detector.sample(900, 1, 10)).toBe(false);
+      expect(detector.sample(1800, 1, 20)).toBe(false);
+      expect(detector.sample(2900, 1, 20)).toBe(true);
+    });
+
+    it('should never report a stall when CPU time is unreadable', () => {
+      const detector = new StallDetector(1000, 0);
+      expect(detector.sample(5000, null, 0)).toBe(false);
+      expect(detector.sample(10_000, null, 0)).toBe(false);
+    });
+  });
+
+  describe('getStallWindowMs()', () => {
+    it('should read PROVE_STALL_WINDOW_MS', () => {
+      expect(getStallWindowMs({ PROVE_STALL_WINDOW_MS: '5000' })).toBe(5000);
+    });
+
+    it('should fall back to the default for unset or invalid values', () => {
+      for (const value of [undefined, '', 'abc', '0', '-1000']) {
+        expect(getStallWindowMs({ PROVE_STALL_WINDOW_MS: value })).toBe(DEFAULT_STALL_WINDOW_MS);
+      }
+    });
+  });
+
+  describe('readProcessCpuTicks()', () => {
+    it.skipIf(!HAS_PROC)('should read CPU ticks of a live process', () => {
+      expect(readProcessCpuTicks(process.pid)).toBeGreaterThanOrEqual(0);
+    });
+
+    it('should return null for a missing process', () => {
+      expect(readProcessCpuTicks(2 ** 30)).toBeNull();
+    });
+  });
+
+  describe('runCommand()', () => {
+    it('should emit one completion event with the expected fields on success', async () => {
+      const events: CommandEvent[] = [];
+      const result = await runCommand('echo', NODE, ['-e', 'process.stdout.write("hello")', '/tmp/wd/x'], {
+        timeoutMs: 10_000,
+        workDir: '/tmp/wd',
+        onEvent: (event) => events.push(event),
+      });
+
+      expect(result.stdout).toBe('hello');
+      expect(events).toHaveLength(1);
+      expect(events[0]).toMatchObject({
+        step: 'echo',
+        argv: [NODE, '-e', 'process.stdout.write("hello")', '<workdir>/x'],
+        exitCode: 0,
+        signal: null,
+        timedOut: false,
+        stdoutBytes: 5,
+        stderrBytes: 0,
+      });
+      expect(events[0].durationMs).toBeGreaterThanOrEqual(0);
+      expect(events[0]).not.toHaveProperty('stdout');
+    });
+
+    it('should report the exit code and stderr size on failure', async () => {
+      const events: CommandEvent[] = [];
+      const run = runCommand('fail', NODE, ['-e', 'process.stderr.write("bad"); process.exit(3)'], {
+        timeoutMs: 10_000,
+        onEvent: (event) => events.push(event),
+      });
+
+      await expect(run).rejects.toThrow('fail failed (exit 3): bad');
+      await expect(run).rejects.toBeInstanceOf(CommandError);
+      expect(events[0]).toMatchObject({ exitCode: 3, stderrBytes: 3, timedOut: false });
+    });
+
+    it('should flag timeouts distinctly', async () => {
+      const events: CommandEvent[] = [];
+      const run = runCommand('sleep', NODE, ['-e', 'setTimeout(() => {}, 10000)'], {
+        timeoutMs: 200,
+        onEvent: (event) => events.push(event),
+      });
+
+      await expect(run).rejects.toThrow('sleep timed out after 0.2s');
+      expect(events[0].timedOut).toBe(true);
+      expect(events[0].exitCode).toBeNull();
+    });
+
+    it.skipIf(!HAS_PROC)('should kill and flag a command that stalls', async () => {
+      const events: CommandEvent[] = [];
+      const run = runCommand('idle', NODE, ['-e', 'setTimeout(() => {}, 10000)'], {
+        timeoutMs: 10_000,
+        stallWindowMs: 400,
+        onEvent: (event) => events.push(event),
+      });
+
+      await expect(run).rejects.toThrow('idle stalled: no CPU or output progress for 0.4s');
+      expect(events[0]).toMatchObject({ stalled: true, timedOut: false, exitCode: null });
+      expect(events[0].durationMs).toBeLessThan(5000);
+    });
+
+    it.skipIf(!HAS_PROC)('should not flag a busy command as stalled', async () => {
+      const result = await runCommand('busy', NODE, ['-e', 'const end = Date.now() + 1000; while (Date.now() < end) {}'], {
+        timeoutMs: 10_000,
+        stallWindowMs: 400,
+      });
+      expect(result.stalled).toBe(false);
+    });
+
+    it('should report binaries that cannot be started', async () => {
+      await expect(
+        runCommand('missing', '/nonexistent/bb', [], { timeoutMs: 1000 }),
+      ).rejects.toThrow('missing could not be started');
+    });
+  });
+
+  describe('exitedNormally()', () => {
+    async function failure(file: string, args: string[], timeoutMs = 10_000): Promise<CommandEvent> {
+      const err = await runCommand('step', file, args, { timeoutMs }).catch((e) => e);
+      expect(err).toBeInstanceOf(CommandError);
+      return (err as CommandError).result;
+    }
+
+    it('should accept a non-zero exit as an answer', async () => {
+      expect(exitedNormally(await failure(NODE, ['-e', 'process.exit(1)']))).toBe(true);
+    });
+
+    it('should reject runs that gave no exit code', async () => {
+      expect(exitedNormally(await failure('/nonexistent/bb', []))).toBe(false);
+      expect(exitedNormally(await failure(NODE, ['-e', 'process.kill(process.pid, "SIGKILL")']))).toBe(false);
+      expect(exitedNormally(await failure(NODE, ['-e', 'setTimeout(() => {}, 10000)'], 200))).toBe(false);
+    });
+  });
+});
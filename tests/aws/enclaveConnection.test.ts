@@ -0,0 +1,391 @@
+import { describe, it, expect, afterEach } from 'vitest';
+import type { Server } from 'node:net';
+import {
+  processRawRequest,
+  ConnectionPool,
+  getVsockWorkers,
+  DEFAULT_VSOCK_WORKERS,
+  type ConnectionContext,
+  type Dispatch,
+} from '../../src/aws/enclaveConnection.js';
+import { sendVsockRequest, exchangeVsockRequests } from '../../src/aws/vsockClient.js';
+import { deferred, testContext, listen, connected } from './fixtures/connection.js';
+
+const panicking: Dispatch = async (request) => {
+  if (request.type === 'crash') {
+    throw new Error('handler exploded');
+  }
+  return { type: request.type, requestId: request.requestId || '' };
+};
+
+describe('enclaveConnection', () => {
+  describe('processRawRequest()', () => {
+    it('should dispatch a valid request', async () => {
+      const ctx = testContext(panicking);
+      const response = await processRawRequest(Buffer.from('{"type":"health","requestId":"r1"}'), ctx);
+      expect(response).toEqual({ type: 'health', requestId: 'r1' });
+      expect(ctx.stats.handlerPanics).toBe(0);
+    });
+
+    it('should turn a handler panic into an INTERNAL error and count it', async () => {
+      const ctx = testContext(panicking);
+      const response = await processRawRequest(Buffer.from('{"type":"crash","requestId":"r2"}'), ctx);
+      expect(response).toMatchObject({
+        type: 'error',
+        requestId: 'r2',
+        code: 'INTERNAL',
+        error: 'Internal enclave error',
+        retryable: false,
+      });
+      expect(response?.errorId).toMatch(/^[0-9a-f-]{36}$/);
+      expect(ctx.stats.handlerPanics).toBe(1);
+    });
+
+    it('should catch synchronous throws from the dispatcher', async () => {
+      const ctx = testContext((() => {
+        throw new TypeError('boom');
+      }) as Dispatch);
+      const response = await processRawRequest(Buffer.from('{"type":"health","requestId":"r5"}'), ctx);
+      expect(response).toMatchObject({ code: 'INTERNAL' });
+      expect(ctx.stats.handlerPanics).toBe(1);
+    });
+
+    it('should reject invalid JSON and non-object requests without dispatching', async () => {
+      const ctx = testContext(panicking);
+      for (const raw of ['{not json', 'null', '[1,2]', '42']) {
+        const response = await processRawRequest(Buffer.from(raw), ctx);
+        expect(response).toMatchObject({ type: 'error', code: 'BAD_REQUEST' });
+        expect(response?.requestId).toMatch(/^srv-/);
+      }
+      expect(ctx.stats.handlerPanics).toBe(0);
+    });
+
+    it('should reject unusable requestIds with a server-generated id', async () => {
+      const ctx = testContext(panicking);
+      const injected = 'r1\n{"level":"info","msg":"forged"}';
+      for (const requestId of [undefined, '', 'x'.repeat(129), injected, '../../etc', 42]) {
+        const raw = Buffer.from(JSON.stringify({ type: 'health', requestId }));
+        const response = await processRawRequest(raw, ctx);
+        expect(response).toMatchObject({ type: 'error', code: 'BAD_REQUEST' });
+        expect(response?.requestId).toMatch(/^srv-[0-9a-f-]{36}$/);
+      }
+    });
+
+    it('should log the full detail under the errorId', async () => {
+      const logged: Record<string, unknown>[] = [];
+      const ctx: ConnectionContext = {
+        ...testContext(panicking),
+        log: (_level, _msg, extra = {}) => logged.push(extra),
+      };
+      const response = await processRawRequest(Buffer.from('{"type":"crash","requestId":"r4"}'), ctx);
+      expect(logged).toContainEqual(expect.objectContaining({
+        action: 'enclave.error.detail',
+        errorId: response?.errorId,
+        error: 'Server error: handler exploded',
+      }));
+    });
+
+    it('should keep the full detail for lookup by errorId', async () => {
+      const ctx = testContext(panicking);
+      const response = await processRawRequest(Buffer.from('{"type":"crash","requestId":"r7"}'), ctx);
+      expect(response?.error).toBe('Internal enclave error');
+      expect(ctx.errors.get(response?.errorId as string)).toMatchObject({
+        errorId: response?.errorId,
+        requestId: 'r7',
+        code: 'INTERNAL',
+        error: 'Server error: handler exploded',
+      });
+    });
+
+    it('should return the full detail when detailLevel is full', async () => {
+      const ctx: ConnectionContext = { ...testContext(panicking), detailLevel: 'full' };
+      const response = await processRawRequest(Buffer.from('{"type":"crash","requestId":"r6"}'), ctx);
+      expect(response?.error).toBe('Server error: handler exploded');
+    });
+
+    it('should leave timing metadata to the handler', async () => {
+      const raw = Buffer.from('{"type":"prove","requestId":"r3"}');
+      const response = await processRawRequest(raw, testContext(async () => ({
+        type: 'proof', requestId: 'r3', timing: { totalMs: 5 },
+      })));
+      expect(response?.timing).toEqual({ totalMs: 5 });
+    });
+
+    it('should reject over-deep bodies before dispatching', async () => {
+      let dispatched = false;
+      const ctx = testContext(async () => {
+        dispatched = true;
+        return { type: 'health', requestId: 'x' };
+      });
+      const nested = '['.repeat(100_000) + ']'.repeat(100_000);
+      const response = await processRawRequest(Buffer.from(nested), ctx);
+      expect(response).toMatchObject({ type: 'error', code: 'BAD_REQUEST', error: 'JSON nesting exceeds depth 64' });
+      expect(dispatched).toBe(false);
+    });
+
+    it('should return null for an empty request', async () => {
+      expect(await processRawRequest(Buffer.alloc(0), testContext(panicking))).toBeNull();
+    });
+  });
+
+  describe('handleConnection()', () => {
+    let server: Server | undefined;
+
+    afterEach(() => {
+      server?.close();
+      server = undefined;
+    });
+
+    async function start(ctx: ConnectionContext): Promise<number> {
+      const listening = await listen(ctx);
+      server = listening.server;
+      return listening.port;
+    }
+
+    it('should keep serving after a handler panic', async () => {
+      const ctx = testContext(panicking);
+      const port = await start(ctx);
+
+      const crashed = await sendVsockRequest('127.0.0.1', port, { type: 'crash' as any, requestId: 'a' }, 2000);
+      expect(crashed).toMatchObject({ type: 'error', code: 'INTERNAL', requestId: 'a' });
+
+      const healthy = await sendVsockRequest('127.0.0.1', port, { type: 'health', requestId: 'b' }, 2000);
+      expect(healthy).toEqual({ type: 'health', requestId: 'b' });
+      expect(ctx.stats.handlerPanics).toBe(1);
+    });
+
+    it('should aggregate request and response bytes across connections', async () => {
+      const ctx = testContext(panicking);
+      const port = await start(ctx);
+
+      for (const requestId of ['a', 'b']) {
+        await sendVsockRequest('127.0.0.1', port, { type: 'health', requestId }, 2000);
+      }
+
+      // The echoing dispatcher answers with the same fields, so both directions move the same bytes
+      const perRequest = Buffer.byteLength(JSON.stringify({ type: 'health', requestId: 'a' }));
+      expect(ctx.stats.transfer.received).toBe(2 * perRequest);
+      expect(ctx.stats.transfer.sent).toBe(2 * perRequest);
+    });
+
+    it('should split the byte totals by request type', async () => {
+      const ctx = testContext(panicking);
+      const port = await start(ctx);
+
+      const health = { type: 'health' as const, requestId: 'a' };
+      const prove = { type: 'prove' as const, requestId: 'b', inputs: { x: 1 } };
+      const untyped = { type: 42, requestId: 'c' } as any;
+      await sendVsockRequest('127.0.0.1', port, health, 2000);
+      await sendVsockRequest('127.0.0.1', port, prove, 2000);
+      const echoed = await sendVsockRequest('127.0.0.1', port, untyped, 2000);
+
+      const size = (value: unknown) => Buffer.byteLength(JSON.stringify(value));
+      expect(ctx.stats.transferByType.toJSON()).toEqual({
+        health: { received: size(health), sent: size(health) },
+        prove: { received: size(prove), sent: size({ type: 'prove', requestId: 'b' }) },
+        invalid: { received: size(untyped), sent: size(echoed) },
+      });
+    });
+
+    it('should answer newline-delimited requests in order on one connection', async () => {
+      const ctx = testContext(panicking);
+      const port = await start(ctx);
+
+      const responses = await exchangeVsockRequests(await connected(port), [
+        { type: 'health', requestId: 'first' },
+        { type: 'attestation', requestId: 'second' },
+      ]);
+      expect(responses).toEqual([
+        { type: 'health', requestId: 'first' },
+        { type: 'attestation', requestId: 'second' },
+      ]);
+    });
+
+    it('should keep serving the connection after a failed request', async () => {
+      const ctx = testContext(panicking);
+      const port = await start(ctx);
+
+      const responses = await exchangeVsockRequests(await connected(port), [
+        { type: 'crash' as any, requestId: 'a' },
+        { type: 'health', requestId: 'b' },
+      ]);
+      expect(responses.map((r) => [r.requestId, r.type, r.code])).toEqual([
+        ['a', 'error', 'INTERNAL'],
+        ['b', 'health', undefined],
+      ]);
+    });
+
+    it('should answer a malformed line and carry on', async () => {
+      const port = await start(testContext(panicking));
+      const socket = await connected(port);
+
+      const chunks: Buffer[] = [];
+      socket.on('data', (chunk: Buffer) => chunks.push(chunk));
+      const closed = new Promise((resolve) => socket.on('end', resolve));
+      socket.end('{"type":"health","requestId":"first"}\n{not json\n\n{"type":"health","requestId":"ok"}\n');
+      await closed;
+
+      const lines = Buffer.concat(chunks).toString('utf-8').split('\n');
+      expect(lines).toHaveLength(4);
+      expect(JSON.parse(lines[0])).toEqual({ type: 'health', requestId: 'first' });
+      expect(JSON.parse(lines[1])).toMatchObject({ type: 'error', code: 'BAD_REQUEST' });
+      expect(JSON.parse(lines[2])).toEqual({ type: 'health', requestId: 'ok' });
+      expect(lines[3]).toBe('');
+    });
+
+    it('should read a multi-line body as one one-shot request', async () => {
+      const ctx = testContext(panicking);
+      const port = await start(ctx);
+      const socket = await connected(port);
+
+      const chunks: Buffer[] = [];
+      socket.on('data', (chunk: Buffer) => chunks.push(chunk));
+      const closed = new Promise((resolve) => socket.on('end', resolve));
+      const body = JSON.stringify({ type: 'health', requestId: 'pretty' }, null, 2) + '\n';
+      socket.end(body);
+      await closed;
+
+      // Answered once, without a trailing newline, as before line framing existed
+      expect(Buffer.concat(chunks).toString('utf-8')).toBe('{"type":"health","requestId":"pretty"}');
+      expect(ctx.stats.transfer.received).toBe(Buffer.byteLength(body));
+    });
+
+    it('should keep the connection open between requests', async () => {
+      const port = await start(testContext(panicking));
+      const socket = await connected(port);
+      const lines: string[] = [];
+      let waiter: (() => void) | undefined;
+      let buffered = '';
+      socket.on('data', (chunk: Buffer) => {
+        buffered += chunk.toString('utf-8');
+        const parts = buffered.split('\n');
+        buffered = parts.pop()!;
+        lines.push(...parts);
+        waiter?.();
+      });
+      const nextLine = (count: number) => new Promise<void>((resolve) => {
+        waiter = () => lines.length >= count && resolve();
+        waiter();
+      });
+
+      socket.write('{"type":"health","requestId":"one"}\n');
+      await nextLine(1);
+      socket.write('{"type":"health","requestId":"two"}\n');
+      await nextLine(2);
+      socket.end();
+
+      expect(lines.map((line) => JSON.parse(line).requestId)).toEqual(['one', 'two']);
+    });
+
+    it('should answer requests in order even when a later one finishes first', async () => {
+      const finished: string[] = [];
+      const ctx = testContext(async (request) => {
+        if (request.type === 'prove') await new Promise((resolve) => setTimeout(resolve, 50));
+        finished.push(request.requestId || '');
+        return { type: request.type, requestId: request.requestId || '' };
+      });
+      const port = await start(ctx);
+
+      const responses = await exchangeVsockRequests(await connected(port), [
+        { type: 'prove', requestId: 'slow' },
+        { type: 'health', requestId: 'fast' },
+      ]);
+      // Both were dispatched at once: the second finished first, but its response waited
+      expect(finished).toEqual(['fast', 'slow']);
+      expect(responses.map((r) => r.requestId)).toEqual(['slow', 'fast']);
+    });
+  });
+
+  describe('ConnectionPool', () => {
+    let server: Server | undefined;
+
+    afterEach(() => {
+      server?.close();
+      server = undefined;
+    });
+
+    const wait = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+    async function startPool(pool: ConnectionPool, ctx: ConnectionContext): Promise<number> {
+      const listening = await listen(ctx, (socket) => pool.serve(socket, 'test', ctx));
+      server = listening.server;
+      return listening.port;
+    }
+
+    it('should read VSOCK_WORKERS', () => {
+      expect(getVsockWorkers({})).toBe(DEFAULT_VSOCK_WORKERS);
+      expect(getVsockWorkers({ VSOCK_WORKERS: '0' })).toBe(DEFAULT_VSOCK_WORKERS);
+      expect(getVsockWorkers({ VSOCK_WORKERS: 'many' })).toBe(DEFAULT_VSOCK_WORKERS);
+      expect(getVsockWorkers({ VSOCK_WORKERS: '2' })).toBe(2);
+    });
+
+    it('should hold connections over the bound until a worker frees up', async () => {
+      const gate = deferred();
+      const answered: string[] = [];
+      const ctx = testContext(async (request) => {
+        if (request.type === 'prove') await gate.promise;
+        answered.push(request.requestId || '');
+        return { type: request.type, requestId: request.requestId || '' };
+      });
+      const pool = new ConnectionPool(1);
+      const port = await startPool(pool, ctx);
+
+      const prove = sendVsockRequest('127.0.0.1', port, { type: 'prove', requestId: 'slow' }, 5000);
+      await wait(20);
+      const health = sendVsockRequest('127.0.0.1', port, { type: 'health', requestId: 'h' }, 5000);
+      await wait(50);
+      expect(pool.busy).toBe(1);
+      expect(pool.queued).toBe(1);
+      expect(answered).toEqual([]);
+
+      gate.resolve();
+      expect(await prove).toEqual({ type: 'prove', requestId: 'slow' });
+      expect(await health).toEqual({ type: 'health', requestId: 'h' });
+      expect(answered).toEqual(['slow', 'h']);
+    });
+
+    it('should answer health on a free worker while a prove holds another', async () => {
+      const gate = deferred();
+      const ctx = testContext(async (request) => {
+        if (request.type === 'prove') await gate.promise;
+        return { type: request.type, requestId: request.requestId || '' };
+      });
+      const pool = new ConnectionPool(2);
+      const port = await startPool(pool, ctx);
+
+      const prove = sendVsockRequest('127.0.0.1', port, { type: 'prove', requestId: 'slow' }, 5000);
+      await wait(20);
+      expect(await sendVsockRequest('127.0.0.1', port, { type: 'health', requestId: 'h' }, 5000)).toEqual({
+        type: 'health', requestId: 'h',
+      });
+
+      gate.resolve();
+      await prove;
+    });
+
+    it('should drop a connection that leaves the queue without sending anything', async () => {
+      const gate = deferred();
+      const ctx = testContext(async (request) => {
+        await gate.promise;
+        return { type: request.type, requestId: request.requestId || '' };
+      });
+      const pool = new ConnectionPool(1);
+      const port = await startPool(pool, ctx);
+
+      const held = sendVsockRequest('127.0.0.1', port, { type: 'prove', requestId: 'held' }, 5000);
+      await wait(20);
+      const abandoned = await connected(port);
+      await wait(20);
+      expect(pool.queued).toBe(1);
+
+      abandoned.destroy();
+      await wait(50);
+      expect(pool.queued).toBe(0);
+
+      gate.resolve();
+      await held;
+      await wait(20);
+      expect(pool.busy).toBe(0);
+    });
+  });
+});
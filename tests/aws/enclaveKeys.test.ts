@@ -0,0 +1,95 @@
+import { describe, it, expect } from 'vitest';
+import * as crypto from 'node:crypto';
+import {
+  generateEnclaveKeyPair,
+  generateSigningKey,
+  keyPairFromPrivateKey,
+  openEnvelope,
+} from '../../src/aws/enclaveKeys.js';
+import { EnclaveError } from '../../src/aws/enclaveProtocol.js';
+import { encryptForTee, computeKeyId } from '../../src/tee/teeKeyExchange.js';
+import {
+  X25519_PKCS8_HEADER,
+  ENCLAVE_PRIVATE_KEY,
+  ENCLAVE_PUBLIC_KEY,
+  ENCLAVE_KEY_ID,
+  PLAINTEXT,
+  ENVELOPE,
+} from './fixtures/payloadVectors.js';
+
+const vectorKeys = keyPairFromPrivateKey(crypto.createPrivateKey({
+  key: Buffer.from(X25519_PKCS8_HEADER + ENCLAVE_PRIVATE_KEY, 'hex'),
+  format: 'der',
+  type: 'pkcs8',
+}));
+
+function openError(payload: typeof ENVELOPE): EnclaveError {
+  try {
+    openEnvelope(payload, vectorKeys);
+  } catch (err) {
+    return err as EnclaveError;
+  }
+  throw new Error('expected openEnvelope to throw');
+}
+
+describe('enclaveKeys', () => {
+  describe('key pair', () => {
+    it('should derive the raw public key and keyId from the private key', () => {
+      expect(vectorKeys.publicKeyRaw.toString('hex')).toBe(ENCLAVE_PUBLIC_KEY);
+      expect(vectorKeys.keyId).toBe(ENCLAVE_KEY_ID);
+    });
+
+    it('should use the same keyId as the client', () => {
+      const keys = generateEnclaveKeyPair();
+      expect(keys.publicKeyRaw).toHaveLength(32);
+      expect(keys.keyId).toBe(computeKeyId(keys.publicKeyRaw.toString('hex')));
+    });
+  });
+
+  describe('signing key', () => {
+    it('should expose the raw Ed25519 public key of the private key', () => {
+      const key = generateSigningKey();
+      expect(key.publicKeyRaw).toHaveLength(32);
+      const signature = crypto.sign(null, Buffer.from('msg'), key.privateKey);
+      const publicKey = crypto.createPublicKey({
+        key: Buffer.concat([Buffer.from('302a300506032b6570032100', 'hex'), key.publicKeyRaw]),
+        format: 'der',
+        type: 'spki',
+      });
+      expect(crypto.verify(null, Buffer.from('msg'), publicKey, signature)).toBe(true);
+    });
+  });
+
+  describe('openEnvelope()', () => {
+    it('should decrypt the fixed test vector', () => {
+      expect(openEnvelope(ENVELOPE, vectorKeys)).toBe(PLAINTEXT);
+    });
+
+    it('should round-trip with the client-side encryptForTee', () => {
+      const keys = generateEnclaveKeyPair();
+      const envelope = encryptForTee(PLAINTEXT, keys.publicKeyRaw.toString('hex'));
+      expect(openEnvelope(envelope, keys)).toBe(PLAINTEXT);
+    });
+
+    it('should report a stale keyId as KEY_ROTATED', () => {
+      const err = openError({ ...ENVELOPE, keyId: '0000000000000000' });
+      expect(err.code).toBe('KEY_ROTATED');
+      expect(err.message).toBe(`Key ID mismatch: expected ${ENCLAVE_KEY_ID}`);
+    });
+
+    it.each([
+      ['tampered ciphertext', { ciphertext: 'ff' + ENVELOPE.ciphertext.slice(2) }],
+      ['tampered authTag', { authTag: '00'.repeat(16) }],
+      ['wrong ephemeral key', { ephemeralPublicKey: ENCLAVE_PUBLIC_KEY }],
+      ['short iv', { iv: '0001' }],
+      ['non-hex ciphertext', { ciphertext: 'zz' }],
+      ['missing authTag', { authTag: undefined as unknown as string }],
+    ])('should fail %s with a generic DECRYPTION_FAILED', (_label, override) => {
+      const err = openError({ ...ENVELOPE, ...override });
+      expect(err).toBeInstanceOf(EnclaveError);
+      expect(err.code).toBe('DECRYPTION_FAILED');
+      expect(err.message).toBe('Decryption failed');
+      expect(err.cause).toBeInstanceOf(Error);
+    });
+  });
+});
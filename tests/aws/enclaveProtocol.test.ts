@@ -0,0 +1,514 @@
+import { describe, it, expect } from 'vitest';
+import * as crypto from 'node:crypto';
+import {
+  validateProveMetadata,
+  validateVerifyRequest,
+  validateAttestationRequest,
+  MAX_USER_DATA_BYTES,
+  effectiveProveSettings,
+  PROVE_METADATA_KEYS,
+  MAX_NONCE_BYTES,
+  parseNonce,
+  parseHex,
+  MAX_TENANT_LENGTH,
+  ENCLAVE_ERROR_CODES,
+  isRetryable,
+  errorResponse,
+  EnclaveError,
+  getErrorDetailLevel,
+  publicErrorMessage,
+  redactErrorResponse,
+  validateRequestId,
+  sanitizeRequestId,
+  generateRequestId,
+  MAX_REQUEST_ID_LENGTH,
+} from '../../src/aws/enclaveProtocol.js';
+
+const STRICT = { lenient: false, maxTimeoutMs: 120_000 };
+const LENIENT = { lenient: true, maxTimeoutMs: 120_000 };
+
+describe('enclaveProtocol', () => {
+  describe('error codes', () => {
+    const RETRYABLE = [
+      'PROVE_TIMEOUT', 'STALLED', 'VERIFY_TIMEOUT', 'ATTESTATION_FAILED', 'KEY_NOT_INITIALIZED', 'SHUTTING_DOWN',
+    ];
+
+    it.each(ENCLAVE_ERROR_CODES.map((code) => [code]))('should map %s to a retryability', (code) => {
+      expect(isRetryable(code)).toBe(RETRYABLE.includes(code));
+    });
+
+    it.each(ENCLAVE_ERROR_CODES.map((code) => [code]))('should derive retryable for %s responses', (code) => {
+      const response = errorResponse('req-1', code, 'message');
+      expect(response).toEqual({
+        type: 'error',
+        requestId: 'req-1',
+        code,
+        error: 'message',
+        retryable: isRetryable(code),
+      });
+    });
+
+    it('should never retry input or constraint failures', () => {
+      for (const code of ['BAD_REQUEST', 'WITNESS_UNSATISFIED', 'CIRCUIT_NOT_FOUND', 'VERIFY_FAILED'] as const) {
+        expect(isRetryable(code)).toBe(false);
+      }
+    });
+
+    it('should not let extra fields override code, error or retryable', () => {
+      const response = errorResponse('req-1', 'BAD_REQUEST', 'bad', { retryable: true, supportedKeys: ['a'] });
+      expect(response.retryable).toBe(false);
+      expect(response.supportedKeys).toEqual(['a']);
+    });
+
+    it('should carry the code on EnclaveError', () => {
+      const err = new EnclaveError('PROVE_FAILED', 'bb exploded');
+      expect(err).toBeInstanceOf(Error);
+      expect(err.code).toBe('PROVE_FAILED');
+      expect(err.message).toBe('bb exploded');
+    });
+  });
+
+  describe('request IDs', () => {
+    it('should accept the ids the parent generates', () => {
+      for (const id of ['health-1718000000000', 'x402-a1b2c3d4e5f60718', crypto.randomUUID(), 'cli-prove-1']) {
+        expect(validateRequestId(id)).toBeNull();
+      }
+      expect(validateRequestId('a'.repeat(MAX_REQUEST_ID_LENGTH))).toBeNull();
+    });
+
+    it('should reject missing, empty and non-string ids', () => {
+      for (const id of [undefined, null, '', 42, {}]) {
+        expect(validateRequestId(id)).toBe('requestId must be a non-empty string');
+      }
+    });
+
+    it('should reject ids over the length limit', () => {
+      expect(validateRequestId('a'.repeat(MAX_REQUEST_ID_LENGTH + 1))).toBe(
+        `requestId must not exceed ${MAX_REQUEST_ID_LENGTH} characters`,
+      );
+    });
+
+    it('should reject control characters, spaces and path separators', () => {
+      for (const id of ['a\nb', 'a\u0000b', 'a b', 'a/b', '..\\x', 'é']) {
+        expect(validateRequestId(id)).toBe('requestId may only contain letters, digits and . _ : -');
+      }
+    });
+
+    it('should sanitize arbitrary values for logs and paths', () => {
+      expect(sanitizeRequestId('req-1')).toBe('req-1');
+      expect(sanitizeRequestId('../a\nb')).toBe('.._a_b');
+      expect(sanitizeRequestId('x'.repeat(500))).toHaveLength(MAX_REQUEST_ID_LENGTH);
+      expect(sanitizeRequestId(undefined)).toBe('');
+    });
+
+    it('should generate valid server-side ids', () => {
+      const id = generateRequestId();
+      expect(id).toMatch(/^srv-/);
+      expect(validateRequestId(id)).toBeNull();
+      expect(generateRequestId()).not.toBe(id);
+    });
+  });
+
+  describe('error detail policy', () => {
+    const CALLER_FACING = ['BAD_REQUEST', 'INVALID_METADATA', 'METADATA_OVERRIDE_REFUSED', 'CIRCUIT_NOT_FOUND'];
+    const PATH_LIKE = /\/(app|tmp|root|usr|dev)\b|\.json\b|stderr|at \S+:\d+/;
+    const detail = 'bb prove failed (exit 1): could not open /app/circuits/x/target/x.json\n    at run (/app/enclave.js:1:2)';
+
+    it('should default to public and honour DETAIL_LEVEL=full', () => {
+      expect(getErrorDetailLevel({})).toBe('public');
+      expect(getErrorDetailLevel({ DETAIL_LEVEL: 'verbose' })).toBe('public');
+      expect(getErrorDetailLevel({ DETAIL_LEVEL: 'full' })).toBe('full');
+    });
+
+    it.each(ENCLAVE_ERROR_CODES.map((code) => [code]))('should keep paths out of public %s responses', (code) => {
+      expect(publicErrorMessage(code)).not.toMatch(PATH_LIKE);
+
+      const redacted = redactErrorResponse(errorResponse('req-1', code, detail), 'public', 'err-1');
+      expect(redacted.errorId).toBe('err-1');
+      expect(redacted.code).toBe(code);
+      if (!CALLER_FACING.includes(code)) {
+        expect(redacted.error).toBe(publicErrorMessage(code));
+        expect(JSON.stringify(redacted)).not.toMatch(PATH_LIKE);
+      }
+    });
+
+    it('should pass caller-facing validation messages through', () => {
+      const redacted = redactErrorResponse(errorResponse('req-1', 'BAD_REQUEST', 'Missing circuitId'), 'public', 'e');
+      expect(redacted.error).toBe('Missing circuitId');
+    });
+
+    it('should keep the full message at the full level', () => {
+      const redacted = redactErrorResponse(errorResponse('req-1', 'PROVE_FAILED', detail), 'full', 'e');
+      expect(redacted.error).toBe(detail);
+      expect(redacted.retryable).toBe(false);
+    });
+  });
+
+  describe('validateProveMetadata()', () => {
+    describe('accepted keys', () => {
+      it('should accept missing metadata', () => {
+        expect(validateProveMetadata(undefined, STRICT)).toEqual({ ok: true, metadata: {}, ignoredKeys: [] });
+        expect(validateProveMetadata(null, STRICT)).toEqual({ ok: true, metadata: {}, ignoredKeys: [] });
+      });
+
+      it('should accept every supported key', () => {
+        const result = validateProveMetadata(
+          {
+            timeoutMs: 60_000,
+            skipAttestation: true,
+            verifyBeforeReturn: false,
+            nonce: '0xABcd',
+            tenant: 'acme-corp',
+            priority: 'high',
+            lowMemory: true,
+            disableZk: false,
+          },
+          STRICT,
+        );
+        expect(result).toEqual({
+          ok: true,
+          metadata: {
+            timeoutMs: 60_000,
+            skipAttestation: true,
+            verifyBeforeReturn: false,
+            nonce: 'abcd',
+            tenant: 'acme-corp',
+            priority: 'high',
+            lowMemory: true,
+            disableZk: false,
+          },
+          ignoredKeys: [],
+        });
+      });
+
+      it('should list the supported keys', () => {
+        expect(PROVE_METADATA_KEYS).toEqual([
+          'timeoutMs', 'skipAttestation', 'verifyBeforeReturn', 'nonce', 'tenant', 'priority',
+          'lowMemory', 'disableZk',
+        ]);
+      });
+
+      it('should default bb settings to the previous behaviour', () => {
+        const result = validateProveMetadata({ timeoutMs: 1000 }, STRICT);
+        expect(result.ok).toBe(true);
+        if (result.ok) expect(effectiveProveSettings(result.metadata)).toEqual({ lowMemory: false, disableZk: false });
+        expect(effectiveProveSettings({ lowMemory: true, disableZk: true })).toEqual({ lowMemory: true, disableZk: true });
+      });
+    });
+
+    describe('invalid values', () => {
+      it('should reject non-object metadata', () => {
+        for (const raw of ['bbPath', 42, [1, 2]]) {
+          const result = validateProveMetadata(raw, STRICT);
+          expect(result.ok).toBe(false);
+          if (!result.ok) expect(result.code).toBe('INVALID_METADATA');
+        }
+      });
+
+      it('should reject non-integer or non-positive timeouts', () => {
+        for (const timeoutMs of [0, -1, 1.5, '1000']) {
+          const result = validateProveMetadata({ timeoutMs }, STRICT);
+          expect(result.ok).toBe(false);
+          if (!result.ok) expect(result.rejectedKeys).toEqual(['timeoutMs']);
+        }
+      });
+
+      it('should reject timeouts above the server maximum', () => {
+        const result = validateProveMetadata({ timeoutMs: 120_001 }, STRICT);
+        expect(result.ok).toBe(false);
+        if (!result.ok) expect(result.error).toContain('120000');
+      });
+
+      it('should reject non-boolean flags', () => {
+        const result = validateProveMetadata({ skipAttestation: 'yes' }, STRICT);
+        expect(result.ok).toBe(false);
+        if (!result.ok) expect(result.error).toBe('metadata.skipAttestation must be a boolean');
+        for (const key of ['lowMemory', 'disableZk']) {
+          const flag = validateProveMetadata({ [key]: 1 }, STRICT);
+          expect(flag.ok).toBe(false);
+          if (!flag.ok) expect(flag.rejectedKeys).toEqual([key]);
+        }
+      });
+
+      it('should reject malformed nonces', () => {
+        for (const nonce of ['', '0x', 'abc', 'zz', 7]) {
+          const result = validateProveMetadata({ nonce }, STRICT);
+          expect(result.ok).toBe(false);
+          if (!result.ok) expect(result.rejectedKeys).toEqual(['nonce']);
+        }
+      });
+
+      it('should reject nonces larger than the NSM limit', () => {
+        const result = validateProveMetadata({ nonce: 'ab'.repeat(MAX_NONCE_BYTES + 1) }, STRICT);
+        expect(result.ok).toBe(false);
+        expect(validateProveMetadata({ nonce: 'ab'.repeat(MAX_NONCE_BYTES) }, STRICT).ok).toBe(true);
+      });
+
+      it('should cap and restrict tenant values', () => {
+        expect(validateProveMetadata({ tenant: 'a'.repeat(MAX_TENANT_LENGTH) }, STRICT).ok).toBe(true);
+        for (const tenant of ['', 'a'.repeat(MAX_TENANT_LENGTH + 1), 'acme corp', 'acme\n', '<script>', 7]) {
+          const result = validateProveMetadata({ tenant }, STRICT);
+          expect(result.ok).toBe(false);
+          if (!result.ok) expect(result.rejectedKeys).toEqual(['tenant']);
+        }
+      });
+
+      it('should only accept known priorities', () => {
+        for (const priority of ['low', 'normal', 'high']) {
+          expect(validateProveMetadata({ priority }, STRICT).ok).toBe(true);
+        }
+        const result = validateProveMetadata({ priority: 'urgent' }, STRICT);
+        expect(result.ok).toBe(false);
+        if (!result.ok) expect(result.error).toBe('metadata.priority must be one of: low, normal, high');
+      });
+    });
+
+    describe('unknown keys', () => {
+      it('should reject unknown keys and list the supported set', () => {
+        const result = validateProveMetadata({ timeoutMs: 1000, region: 'eu' }, STRICT);
+        expect(result).toEqual({
+          ok: false,
+          code: 'INVALID_METADATA',
+          error: `Unknown metadata keys: region. Supported: ${PROVE_METADATA_KEYS.join(', ')}`,
+          supportedKeys: PROVE_METADATA_KEYS,
+          rejectedKeys: ['region'],
+        });
+      });
+
+      it('should ignore unknown keys in lenient mode', () => {
+        const result = validateProveMetadata({ timeoutMs: 1000, region: 'eu' }, LENIENT);
+        expect(result).toEqual({ ok: true, metadata: { timeoutMs: 1000 }, ignoredKeys: ['region'] });
+      });
+    });
+
+    describe('refused keys', () => {
+      it('should refuse binary path overrides', () => {
+        const result = validateProveMetadata({ bbPath: '/custom/bb' }, STRICT);
+        expect(result.ok).toBe(false);
+        if (!result.ok) {
+          expect(result.code).toBe('METADATA_OVERRIDE_REFUSED');
+          expect(result.rejectedKeys).toEqual(['bbPath']);
+          expect(result.error).toContain('measured into its image');
+        }
+      });
+
+      it('should refuse binary overrides even in lenient mode', () => {
+        for (const key of ['nargoPath', 'bb_binary', 'NARGO_BIN', 'bb']) {
+          const result = validateProveMetadata({ [key]: '/tmp/x' }, LENIENT);
+          expect(result.ok).toBe(false);
+          if (!result.ok) expect(result.code).toBe('METADATA_OVERRIDE_REFUSED');
+        }
+      });
+
+      it('should report every refused key at once', () => {
+        const result = validateProveMetadata({ bbPath: '/a', nargoPath: '/b', timeoutMs: 1000 }, STRICT);
+        expect(result.ok).toBe(false);
+        if (!result.ok) expect(result.rejectedKeys).toEqual(['bbPath', 'nargoPath']);
+      });
+
+      it('should refuse disableZk unless the enclave allows it', () => {
+        const refused = validateProveMetadata({ disableZk: true }, LENIENT);
+        expect(refused.ok).toBe(false);
+        if (!refused.ok) {
+          expect(refused.code).toBe('METADATA_OVERRIDE_REFUSED');
+          expect(refused.rejectedKeys).toEqual(['disableZk']);
+        }
+        expect(validateProveMetadata({ disableZk: true }, { ...STRICT, allowDisableZk: true })).toEqual({
+          ok: true, metadata: { disableZk: true }, ignoredKeys: [],
+        });
+      });
+
+      it('should refuse disableZk for an encrypted prove even when allowed', () => {
+        const result = validateProveMetadata({ disableZk: true }, { ...STRICT, allowDisableZk: true, encrypted: true });
+        expect(result.ok).toBe(false);
+        if (!result.ok) {
+          expect(result.code).toBe('METADATA_OVERRIDE_REFUSED');
+          expect(result.error).toContain('encrypted');
+        }
+        const zk = validateProveMetadata({ disableZk: false }, { ...STRICT, encrypted: true });
+        expect(zk.ok).toBe(true);
+      });
+    });
+  });
+
+  describe('parseNonce()', () => {
+    it('should normalize hex nonces', () => {
+      expect(parseNonce('0xABcd', 'nonce')).toEqual({ ok: true, nonce: 'abcd' });
+      expect(parseNonce('ab'.repeat(MAX_NONCE_BYTES), 'nonce').ok).toBe(true);
+    });
+
+    it('should name the field in errors', () => {
+      for (const raw of ['', '0x', 'abc', 'zz', 7, undefined]) {
+        expect(parseNonce(raw, 'nonce')).toEqual({ ok: false, error: 'nonce must be a non-empty even-length hex string' });
+      }
+      expect(parseNonce('ab'.repeat(MAX_NONCE_BYTES + 1), 'nonce')).toEqual({
+        ok: false, error: `nonce must not exceed ${MAX_NONCE_BYTES} bytes`,
+      });
+    });
+  });
+
+  describe('parseHex()', () => {
+    const bytes = (hex: string) => ({ ok: true, bytes: Buffer.from(hex, 'hex') });
+
+    it('should decode even-length hex the same under both policies', () => {
+      for (const policy of ['strict', 'lenient'] as const) {
+        expect(parseHex('0xABcd', 'f', policy)).toEqual(bytes('abcd'));
+        expect(parseHex('abcd', 'f', policy)).toEqual(bytes('abcd'));
+      }
+    });
+
+    it('should decode "" and bare "0x" to zero bytes under both policies', () => {
+      for (const policy of ['strict', 'lenient'] as const) {
+        expect(parseHex('', 'f', policy)).toEqual(bytes(''));
+        expect(parseHex('0x', 'f', policy)).toEqual(bytes(''));
+      }
+    });
+
+    it('should reject odd-length hex under the strict policy', () => {
+      expect(parseHex('0xabc', 'proof', 'strict')).toEqual({
+        ok: false, error: 'proof has odd length (3 hex digits received, strict policy requires an even length)',
+      });
+      expect(parseHex('1', 'proof', 'strict')).toEqual({
+        ok: false, error: 'proof has odd length (1 hex digits received, strict policy requires an even length)',
+      });
+    });
+
+    it('should left-pad odd-length hex under the lenient policy', () => {
+      expect(parseHex('0xabc', 'f', 'lenient')).toEqual(bytes('0abc'));
+      expect(parseHex('1', 'f', 'lenient')).toEqual(bytes('01'));
+    });
+
+    it('should name the field, length and policy for non-hex input', () => {
+      expect(parseHex('0xzz1', 'proofHash', 'lenient')).toEqual({
+        ok: false, error: 'proofHash contains non-hex characters (3 characters received, lenient policy)',
+      });
+      expect(parseHex('0x0x', 'proof', 'strict')).toEqual({
+        ok: false, error: 'proof contains non-hex characters (2 characters received, strict policy)',
+      });
+      expect(parseHex(42, 'proof', 'strict')).toEqual({ ok: false, error: 'proof must be a hex string (strict policy)' });
+      expect(parseHex(null, 'f', 'lenient')).toEqual({ ok: false, error: 'f must be a hex string (lenient policy)' });
+    });
+  });
+
+  describe('validateVerifyRequest()', () => {
+    const VALID = { circuitId: 'coinbase_attestation', proof: '0xabcd', publicInputs: ['0x0001', '02'] };
+
+    it('should decode the proof and concatenate public inputs', () => {
+      const result = validateVerifyRequest(VALID);
+      expect(result.ok).toBe(true);
+      if (result.ok) {
+        expect(result.circuitId).toBe('coinbase_attestation');
+        expect(result.proof.toString('hex')).toBe('abcd');
+        expect(result.publicInputs.toString('hex')).toBe('000102');
+        expect(result.disableZk).toBe(false);
+      }
+    });
+
+    it('should carry disableZk for non-ZK proofs', () => {
+      const result = validateVerifyRequest({ ...VALID, disableZk: true });
+      expect(result.ok).toBe(true);
+      if (result.ok) expect(result.disableZk).toBe(true);
+      expect(validateVerifyRequest({ ...VALID, disableZk: 'yes' })).toEqual({
+        ok: false, error: 'disableZk must be a boolean',
+      });
+    });
+
+    it('should accept circuits without public inputs', () => {
+      const result = validateVerifyRequest({ ...VALID, publicInputs: [] });
+      expect(result.ok).toBe(true);
+      if (result.ok) expect(result.publicInputs).toHaveLength(0);
+    });
+
+    it('should reject a missing circuitId', () => {
+      for (const circuitId of [undefined, '', 7]) {
+        expect(validateVerifyRequest({ ...VALID, circuitId })).toEqual({ ok: false, error: 'Missing circuitId' });
+      }
+    });
+
+    it('should reject a missing proof', () => {
+      for (const proof of [undefined, '', '0x']) {
+        expect(validateVerifyRequest({ ...VALID, proof })).toEqual({ ok: false, error: 'Missing proof' });
+      }
+    });
+
+    it('should parse proofs strictly', () => {
+      expect(validateVerifyRequest({ ...VALID, proof: '0xabc' })).toEqual({
+        ok: false, error: 'proof has odd length (3 hex digits received, strict policy requires an even length)',
+      });
+      expect(validateVerifyRequest({ ...VALID, proof: 'zz' })).toEqual({
+        ok: false, error: 'proof contains non-hex characters (2 characters received, strict policy)',
+      });
+      for (const proof of [42, ['ab']]) {
+        expect(validateVerifyRequest({ ...VALID, proof })).toEqual({
+          ok: false, error: 'proof must be a hex string (strict policy)',
+        });
+      }
+    });
+
+    it('should left-pad odd-length public inputs', () => {
+      const result = validateVerifyRequest({ ...VALID, publicInputs: ['0x1', 'abc', '0x'] });
+      expect(result.ok).toBe(true);
+      if (result.ok) expect(result.publicInputs.toString('hex')).toBe('010abc');
+    });
+
+    it('should reject malformed public inputs', () => {
+      expect(validateVerifyRequest({ ...VALID, publicInputs: undefined })).toEqual({
+        ok: false, error: 'publicInputs must be an array of hex strings',
+      });
+      expect(validateVerifyRequest({ ...VALID, publicInputs: '0x01' })).toEqual({
+        ok: false, error: 'publicInputs must be an array of hex strings',
+      });
+      expect(validateVerifyRequest({ ...VALID, publicInputs: ['0x01', '0xg1'] })).toEqual({
+        ok: false, error: 'publicInputs[1] contains non-hex characters (2 characters received, lenient policy)',
+      });
+      expect(validateVerifyRequest({ ...VALID, publicInputs: [1] })).toEqual({
+        ok: false, error: 'publicInputs[0] must be a hex string (lenient policy)',
+      });
+    });
+  });
+
+  describe('validateAttestationRequest()', () => {
+    it('should accept requests without a nonce', () => {
+      const result = validateAttestationRequest({ proofHash: '0xABcd' });
+      expect(result).toEqual({ ok: true, userData: Buffer.from('abcd', 'hex') });
+    });
+
+    it('should normalize the nonce', () => {
+      const result = validateAttestationRequest({ proofHash: 'abcd', nonce: '0xFF00' });
+      expect(result).toEqual({ ok: true, userData: Buffer.from('abcd', 'hex'), nonce: 'ff00' });
+    });
+
+    it('should treat a missing proofHash as empty user data', () => {
+      for (const proofHash of [undefined, '', '0x']) {
+        const result = validateAttestationRequest({ proofHash });
+        expect(result.ok).toBe(true);
+        if (result.ok) expect(result.userData).toHaveLength(0);
+      }
+    });
+
+    it('should left-pad an odd-length proofHash', () => {
+      expect(validateAttestationRequest({ proofHash: '0xabc' })).toEqual({ ok: true, userData: Buffer.from('0abc', 'hex') });
+    });
+
+    it('should reject a proofHash that is not hex', () => {
+      expect(validateAttestationRequest({ proofHash: 'ab cd' })).toEqual({
+        ok: false, error: 'proofHash contains non-hex characters (5 characters received, lenient policy)',
+      });
+      expect(validateAttestationRequest({ proofHash: 42 })).toEqual({
+        ok: false, error: 'proofHash must be a hex string (lenient policy)',
+      });
+    });
+
+    it('should reject a proofHash larger than the NSM limit', () => {
+      expect(validateAttestationRequest({ proofHash: 'ab'.repeat(MAX_USER_DATA_BYTES + 1) })).toEqual({
+        ok: false, error: `proofHash must not exceed ${MAX_USER_DATA_BYTES} bytes`,
+      });
+    });
+
+    it('should reject a malformed nonce', () => {
+      expect(validateAttestationRequest({ proofHash: 'ab', nonce: 'abc' })).toEqual({
+        ok: false, error: 'nonce must be a non-empty even-length hex string',
+      });
+    });
+  });
+});
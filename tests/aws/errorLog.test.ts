@@ -0,0 +1,26 @@
+import { describe, it, expect } from 'vitest';
+import { ErrorLog, type ErrorDetail } from '../../src/aws/errorLog.js';
+
+function detail(errorId: string): ErrorDetail {
+  return { errorId, requestId: `req-${errorId}`, code: 'INTERNAL', error: `detail ${errorId}`, at: '2026-01-01T00:00:00.000Z' };
+}
+
+describe('ErrorLog', () => {
+  it('should return recorded details by errorId', () => {
+    const log = new ErrorLog();
+    log.record(detail('a'));
+    expect(log.get('a')).toEqual(detail('a'));
+    expect(log.get('missing')).toBeUndefined();
+  });
+
+  it('should drop the oldest entries past its capacity', () => {
+    const log = new ErrorLog(2);
+    for (const errorId of ['a', 'b', 'c']) {
+      log.record(detail(errorId));
+    }
+    expect(log.size).toBe(2);
+    expect(log.get('a')).toBeUndefined();
+    expect(log.get('b')).toEqual(detail('b'));
+    expect(log.get('c')).toEqual(detail('c'));
+  });
+});
@@ -0,0 +1,56 @@
+/**
+ * Shared fixtures for tests that drive the enclave connection handler over a
+ * loopback TCP server: a ConnectionContext with quiet defaults, a listener
+ * that serves every connection with handleConnection, and a manual gate for
+ * holding a request open.
+ */
+
+import { createServer, connect, type Server, type Socket, type AddressInfo } from 'node:net';
+import {
+  handleConnection,
+  createServerStats,
+  type ConnectionContext,
+  type Dispatch,
+} from '../../../src/aws/enclaveConnection.js';
+import { DEFAULT_JSON_LIMITS } from '../../../src/aws/jsonGuard.js';
+import { ErrorLog } from '../../../src/aws/errorLog.js';
+
+/** A promise the test resolves by hand */
+export function deferred() {
+  let resolve!: () => void;
+  const promise = new Promise<void>((r) => { resolve = r; });
+  return { promise, resolve };
+}
+
+/** Context with fresh counters, no logging and public error detail */
+export function testContext(dispatch: Dispatch, overrides: Partial<ConnectionContext> = {}): ConnectionContext {
+  return {
+    dispatch,
+    stats: createServerStats(),
+    log: () => {},
+    detailLevel: 'public',
+    jsonLimits: DEFAULT_JSON_LIMITS,
+    errors: new ErrorLog(),
+    ...overrides,
+  };
+}
+
+/**
+ * Serve ctx on an ephemeral loopback port, with handleConnection unless
+ * onConnection is given. Close the server in afterEach.
+ */
+export function listen(
+  ctx: ConnectionContext,
+  onConnection: (socket: Socket) => void = (socket) => handleConnection(socket, 'test', ctx),
+): Promise<{ server: Server; port: number }> {
+  return new Promise((resolve) => {
+    const server = createServer({ allowHalfOpen: true }, onConnection);
+    server.listen(0, '127.0.0.1', () => resolve({ server, port: (server.address() as AddressInfo).port }));
+  });
+}
+
+/** Open a half-open-capable client socket and wait until it is connected */
+export function connected(port: number): Promise<Socket> {
+  const socket = connect({ host: '127.0.0.1', port, allowHalfOpen: true });
+  return new Promise((resolve) => socket.on('connect', () => resolve(socket)));
+}
@@ -0,0 +1,24 @@
+/**
+ * A stand-in for the bb binary, run with node: `prove` reads the circuit, vk
+ * and witness and writes proof and public_inputs to -o, like bb does. Set
+ * FAKE_BB_PROOF_BYTES to change the proof size or to 0 to write no proof.
+ */
+
+import { runCommand } from '../../../src/aws/enclaveCommand.js';
+import type { BbProveDeps } from '../../../src/aws/bbProve.js';
+
+const FAKE_BB = `
+const fs = require('node:fs');
+const path = require('node:path');
+const arg = (flag) => process.argv[process.argv.indexOf(flag) + 1];
+JSON.parse(fs.readFileSync(arg('-b'), 'utf8'));
+fs.readFileSync(arg('-k'));
+fs.readFileSync(arg('-w'));
+const proofBytes = Number(process.env.FAKE_BB_PROOF_BYTES ?? 64);
+if (proofBytes > 0) fs.writeFileSync(path.join(arg('-o'), 'proof'), Buffer.alloc(proofBytes, 1));
+fs.writeFileSync(path.join(arg('-o'), 'public_inputs'), Buffer.alloc(32, 2));
+`;
+
+/** BbProveDeps.runBb that runs the stand-in instead of bb */
+export const fakeBb: BbProveDeps['runBb'] = (label, args, options) =>
+  runCommand(label, process.execPath, ['-e', FAKE_BB, '--', ...args], options);
@@ -0,0 +1,27 @@
+/**
+ * Regression corpus for the vsock JSON guard (src/aws/jsonGuard.ts).
+ *
+ * Each entry is checked against the limits in tests/aws/jsonGuard.test.ts.
+ * Add a case here whenever a body is found that the scanner miscounts.
+ */
+
+export interface CorpusCase {
+  name: string;
+  body: string;
+  /** Expected result with { maxDepth: 4, maxTokens: 32 } */
+  accepted: boolean;
+}
+
+export const JSON_GUARD_CORPUS: CorpusCase[] = [
+  { name: 'brackets inside strings', body: '{"a":"[[[[[[[[{{{{{{{"}', accepted: true },
+  { name: 'escaped quote before brackets', body: '{"a":"\\"[[[[[[[[[[","b":1}', accepted: true },
+  { name: 'escaped backslash ends string', body: '{"a":"\\\\","b":[[[[[1]]]]]}', accepted: false },
+  { name: 'unicode escapes and multi-byte text', body: '{"a":"\\u005b\\u005b","b":"ééé[[[[[["}', accepted: true },
+  { name: 'depth exactly at limit', body: '[[[[1]]]]', accepted: true },
+  { name: 'depth one past limit', body: '[[[[[1]]]]]', accepted: false },
+  { name: 'unbalanced closers do not underflow into acceptance', body: ']]]]][[[[[', accepted: false },
+  { name: 'many scalars', body: '[' + Array.from({ length: 40 }, (_, i) => i).join(',') + ']', accepted: false },
+  { name: 'long number is one token', body: '[' + '9'.repeat(10_000) + ']', accepted: true },
+  { name: 'literals are tokens', body: '[true,false,null]', accepted: true },
+  { name: 'prove request shape', body: '{"type":"prove","requestId":"r1","circuitId":"c","inputs":{"a":[1,2,3]}}', accepted: true },
+];
@@ -0,0 +1,24 @@
+/**
+ * Fixed E2E payload vector. The X25519 keys and shared secret are the RFC 7748
+ * §6.1 test vectors (enclave = Bob, ephemeral = Alice); ciphertext and tag
+ * were produced with AES-256-GCM under SHA-256(shared secret) and the IV below.
+ */
+
+/** PKCS#8 prefix for a raw 32-byte X25519 private key */
+export const X25519_PKCS8_HEADER = '302e020100300506032b656e04220420';
+
+export const ENCLAVE_PRIVATE_KEY = '5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb';
+export const ENCLAVE_PUBLIC_KEY = 'de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f';
+export const ENCLAVE_KEY_ID = 'f35e5616160a30bf';
+
+export const PLAINTEXT = '{"circuitId":"coinbase_attestation","inputs":{"signal_hash":"0xab"}}';
+
+export const ENVELOPE = {
+  ephemeralPublicKey: '8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a',
+  iv: '000102030405060708090a0b',
+  ciphertext:
+    '7ccca629b00afae488917982b7b3bb134cbd23fe93181ad943ce9cfe22aeca2a'
+    + '7c3371e8f887efd4059eb89035945f3336509c4a56537a53053d0648af398616277d971c',
+  authTag: '92dc43e0cce1890e4094a701faba993d',
+  keyId: ENCLAVE_KEY_ID,
+};
@@ -0,0 +1,68 @@
+import { describe, it, expect } from 'vitest';
+import { checkJsonLimits, getJsonLimits, DEFAULT_JSON_LIMITS } from '../../src/aws/jsonGuard.js';
+import { JSON_GUARD_CORPUS } from './fixtures/jsonGuardCorpus.js';
+
+const LIMITS = { maxBytes: 1024 * 1024, maxDepth: 4, maxTokens: 32 };
+
+function nested(depth: number): Buffer {
+  return Buffer.from('['.repeat(depth) + '0' + ']'.repeat(depth));
+}
+
+describe('jsonGuard', () => {
+  describe('getJsonLimits()', () => {
+    it('should use defaults and honour valid overrides', () => {
+      expect(getJsonLimits({})).toEqual(DEFAULT_JSON_LIMITS);
+      expect(getJsonLimits({ VSOCK_MAX_JSON_DEPTH: '16', VSOCK_MAX_JSON_TOKENS: 'lots' })).toEqual({
+        ...DEFAULT_JSON_LIMITS,
+        maxDepth: 16,
+      });
+    });
+  });
+
+  describe('checkJsonLimits()', () => {
+    it('should accept nesting at the limit and reject one level beyond', () => {
+      for (const depth of [1, 2, 3, 4]) {
+        expect(checkJsonLimits(nested(depth), LIMITS)).toBeNull();
+      }
+      expect(checkJsonLimits(nested(5), LIMITS)).toBe('JSON nesting exceeds depth 4');
+    });
+
+    it('should reject generated payloads far beyond the depth limit quickly', () => {
+      for (const depth of [65, 1_000, 100_000, 1_000_000]) {
+        const body = nested(depth);
+        const started = Date.now();
+        expect(checkJsonLimits(body, { ...DEFAULT_JSON_LIMITS, maxBytes: body.length })).toBe(
+          'JSON nesting exceeds depth 64',
+        );
+        expect(Date.now() - started).toBeLessThan(1000);
+      }
+    });
+
+    it('should count nested objects towards depth', () => {
+      expect(checkJsonLimits(Buffer.from('{"a":{"b":{"c":{"d":1}}}}'), LIMITS)).toBeNull();
+      expect(checkJsonLimits(Buffer.from('{"a":{"b":{"c":{"d":{"e":1}}}}}'), LIMITS)).toBe(
+        'JSON nesting exceeds depth 4',
+      );
+    });
+
+    it('should reject bodies over the token limit', () => {
+      const atLimit = Buffer.from('[' + Array(31).fill(1).join(',') + ']');
+      const overLimit = Buffer.from('[' + Array(32).fill(1).join(',') + ']');
+      expect(checkJsonLimits(atLimit, LIMITS)).toBeNull();
+      expect(checkJsonLimits(overLimit, LIMITS)).toBe('JSON exceeds 32 tokens');
+    });
+
+    it('should reject bodies over the size limit', () => {
+      expect(checkJsonLimits(Buffer.alloc(11, 0x20), { ...LIMITS, maxBytes: 10 })).toBe('Request exceeds 10 bytes');
+    });
+
+    it.each(JSON_GUARD_CORPUS.map((c) => [c.name, c]))('corpus: %s', (_name, corpusCase) => {
+      const result = checkJsonLimits(Buffer.from(corpusCase.body), LIMITS);
+      if (corpusCase.accepted) {
+        expect(result).toBeNull();
+      } else {
+        expect(result).not.toBeNull();
+      }
+    });
+  });
+});
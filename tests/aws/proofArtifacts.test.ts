@@ -0,0 +1,104 @@
+import { describe, it, expect } from 'vitest';
+import {
+  countPublicInputFields,
+  validateProofArtifacts,
+  getProofSizeBounds,
+  DEFAULT_PROOF_SIZE_BOUNDS,
+  FIELD_BYTES,
+} from '../../src/aws/proofArtifacts.js';
+
+const BOUNDS = { minBytes: 1024, maxBytes: 16 * 1024 };
+const PROOF_BYTES = 14_080;
+
+// Shape of a compiled Noir circuit's `abi` section
+const FIXTURE_ABI = {
+  parameters: [
+    { name: 'signal_hash', type: { kind: 'array', length: 32, type: { kind: 'integer', sign: 'unsigned', width: 8 } }, visibility: 'public' },
+    { name: 'merkle_root', type: { kind: 'field' }, visibility: 'public' },
+    { name: 'signature', type: { kind: 'array', length: 64, type: { kind: 'integer', sign: 'unsigned', width: 8 } }, visibility: 'private' },
+    {
+      name: 'meta',
+      type: {
+        kind: 'struct',
+        path: 'Meta',
+        fields: [
+          { name: 'flag', type: { kind: 'boolean' } },
+          { name: 'country', type: { kind: 'string', length: 2 } },
+        ],
+      },
+      visibility: 'public',
+    },
+  ],
+  return_type: { abi_type: { kind: 'tuple', fields: [{ kind: 'field' }, { kind: 'field' }] }, visibility: 'public' },
+};
+
+describe('proofArtifacts', () => {
+  describe('countPublicInputFields()', () => {
+    it('should flatten public parameters and the public return value', () => {
+      // 32 (signal_hash) + 1 (merkle_root) + 3 (meta) + 2 (return tuple)
+      expect(countPublicInputFields(FIXTURE_ABI)).toBe(38);
+    });
+
+    it('should skip private parameters and private return values', () => {
+      expect(countPublicInputFields({
+        parameters: [{ name: 'x', type: { kind: 'field' }, visibility: 'private' }],
+        return_type: { abi_type: { kind: 'field' }, visibility: 'private' },
+      })).toBe(0);
+    });
+
+    it('should return null for a missing ABI or an unknown type', () => {
+      expect(countPublicInputFields(undefined)).toBeNull();
+      expect(countPublicInputFields({})).toBeNull();
+      expect(countPublicInputFields({
+        parameters: [{ name: 'x', type: { kind: 'mystery' }, visibility: 'public' }],
+      })).toBeNull();
+    });
+  });
+
+  describe('validateProofArtifacts()', () => {
+    it('should accept complete artifacts', () => {
+      expect(validateProofArtifacts(PROOF_BYTES, 38 * FIELD_BYTES, 38, BOUNDS)).toBeNull();
+    });
+
+    it('should reject truncated proofs', () => {
+      expect(validateProofArtifacts(12, 38 * FIELD_BYTES, 38, BOUNDS)).toBe('proof is 12 bytes, expected 1024-16384');
+      expect(validateProofArtifacts(PROOF_BYTES - 7, 38 * FIELD_BYTES, 38, BOUNDS)).toBe(
+        `proof is ${PROOF_BYTES - 7} bytes, not a multiple of 32`,
+      );
+    });
+
+    it('should reject padded or oversized proofs', () => {
+      expect(validateProofArtifacts(PROOF_BYTES + 1, 38 * FIELD_BYTES, 38, BOUNDS)).toContain('not a multiple of 32');
+      expect(validateProofArtifacts(BOUNDS.maxBytes + FIELD_BYTES, 38 * FIELD_BYTES, 38, BOUNDS)).toContain(
+        'expected 1024-16384',
+      );
+    });
+
+    it('should reject public inputs that are not whole fields', () => {
+      expect(validateProofArtifacts(PROOF_BYTES, 38 * FIELD_BYTES - 1, 38, BOUNDS)).toBe(
+        `public_inputs is ${38 * FIELD_BYTES - 1} bytes, not a multiple of 32`,
+      );
+    });
+
+    it('should reject a public input count that differs from the ABI', () => {
+      expect(validateProofArtifacts(PROOF_BYTES, 37 * FIELD_BYTES, 38, BOUNDS)).toBe(
+        'public_inputs has 37 fields, circuit declares 38',
+      );
+      expect(validateProofArtifacts(PROOF_BYTES, 0, 38, BOUNDS)).toBe('public_inputs has 0 fields, circuit declares 38');
+    });
+
+    it('should skip the count check when the ABI could not be sized', () => {
+      expect(validateProofArtifacts(PROOF_BYTES, 5 * FIELD_BYTES, null, BOUNDS)).toBeNull();
+    });
+  });
+
+  describe('getProofSizeBounds()', () => {
+    it('should use defaults and honour overrides', () => {
+      expect(getProofSizeBounds({})).toEqual(DEFAULT_PROOF_SIZE_BOUNDS);
+      expect(getProofSizeBounds({ PROOF_MIN_BYTES: '8192', PROOF_MAX_BYTES: 'x' })).toEqual({
+        minBytes: 8192,
+        maxBytes: DEFAULT_PROOF_SIZE_BOUNDS.maxBytes,
+      });
+    });
+  });
+});
@@ -0,0 +1,143 @@
+import { describe, it, expect, afterEach } from 'vitest';
+import type { Server } from 'node:net';
+import {
+  ProveLimiter,
+  getProveConcurrency,
+  getPriorityAgingMs,
+  DEFAULT_PRIORITY_AGING_MS,
+} from '../../src/aws/proveLimiter.js';
+import { sendVsockRequest } from '../../src/aws/vsockClient.js';
+import { deferred, testContext, listen } from './fixtures/connection.js';
+
+const tick = () => new Promise((resolve) => setImmediate(resolve));
+
+describe('proveLimiter', () => {
+  describe('getProveConcurrency()', () => {
+    it('should default to one prove at a time', () => {
+      expect(getProveConcurrency({})).toBe(1);
+      expect(getProveConcurrency({ PROVE_CONCURRENCY: '0' })).toBe(1);
+      expect(getProveConcurrency({ PROVE_CONCURRENCY: 'many' })).toBe(1);
+    });
+
+    it('should honour PROVE_CONCURRENCY', () => {
+      expect(getProveConcurrency({ PROVE_CONCURRENCY: '3' })).toBe(3);
+    });
+  });
+
+  describe('getPriorityAgingMs()', () => {
+    it('should read PROVE_PRIORITY_AGING_MS and fall back for invalid values', () => {
+      expect(getPriorityAgingMs({ PROVE_PRIORITY_AGING_MS: '5000' })).toBe(5000);
+      for (const value of [undefined, 'abc', '0', '-1']) {
+        expect(getPriorityAgingMs({ PROVE_PRIORITY_AGING_MS: value })).toBe(DEFAULT_PRIORITY_AGING_MS);
+      }
+    });
+  });
+
+  describe('ProveLimiter', () => {
+    it('should never run more tasks than its concurrency', async () => {
+      const limiter = new ProveLimiter(2);
+      let current = 0;
+      let peak = 0;
+      const gates = [deferred(), deferred(), deferred(), deferred()];
+
+      const runs = gates.map((gate) => limiter.run(async () => {
+        current += 1;
+        peak = Math.max(peak, current);
+        await gate.promise;
+        current -= 1;
+      }));
+      await tick();
+      expect(limiter.running).toBe(2);
+      expect(limiter.queued).toBe(2);
+
+      gates.forEach((gate) => gate.resolve());
+      await Promise.all(runs);
+      expect(peak).toBe(2);
+      expect(limiter.running).toBe(0);
+      expect(limiter.queued).toBe(0);
+    });
+
+    it('should start waiters by priority, then in arrival order', async () => {
+      const limiter = new ProveLimiter(1);
+      const order: string[] = [];
+      const gate = deferred();
+
+      const first = limiter.run(() => gate.promise);
+      const runs = [
+        limiter.run(async () => { order.push('low'); }, 'low'),
+        limiter.run(async () => { order.push('normal-1'); }),
+        limiter.run(async () => { order.push('high'); }, 'high'),
+        limiter.run(async () => { order.push('normal-2'); }, 'normal'),
+      ];
+      gate.resolve();
+      await Promise.all([first, ...runs]);
+
+      expect(order).toEqual(['high', 'normal-1', 'normal-2', 'low']);
+    });
+
+    it('should not starve a low-priority prove under sustained high-priority load', async () => {
+      let clock = 0;
+      const limiter = new ProveLimiter(1, 1000, () => clock);
+      const order: string[] = [];
+
+      // Each high-priority prove takes 600ms and queues the next one, so one is always waiting
+      let highs = 0;
+      const high = (): Promise<void> => limiter.run(async () => {
+        order.push(`high-${++highs}`);
+        clock += 600;
+        if (highs < 10) void high();
+      }, 'high');
+
+      const gate = deferred();
+      const first = limiter.run(() => gate.promise, 'high');
+      const low = limiter.run(async () => { order.push('low'); }, 'low');
+      void high();
+      gate.resolve();
+      await low;
+      await first;
+
+      // low (rank 2) has aged to rank 0 by the fourth release (2400ms) and, having
+      // arrived first, wins the tie with the newest high waiter
+      expect(order.slice(0, 5)).toEqual(['high-1', 'high-2', 'high-3', 'high-4', 'low']);
+      while (highs < 10) await tick();
+    });
+
+    it('should release the slot when a task fails', async () => {
+      const limiter = new ProveLimiter(1);
+      await expect(limiter.run(async () => { throw new Error('bb failed'); })).rejects.toThrow('bb failed');
+      await expect(limiter.run(async () => 'ok')).resolves.toBe('ok');
+      expect(limiter.running).toBe(0);
+    });
+  });
+
+  describe('with the connection handler', () => {
+    let server: Server | undefined;
+
+    afterEach(() => {
+      server?.close();
+      server = undefined;
+    });
+
+    it('should answer health while a prove holds the only slot', async () => {
+      const limiter = new ProveLimiter(1);
+      const proveGate = deferred();
+      const ctx = testContext(async (request) => {
+        if (request.type === 'prove') await limiter.run(() => proveGate.promise);
+        return { type: request.type, requestId: request.requestId || '' };
+      });
+      const listening = await listen(ctx);
+      server = listening.server;
+
+      const prove = sendVsockRequest('127.0.0.1', listening.port, { type: 'prove', requestId: 'slow' }, 5000);
+      await new Promise((resolve) => setTimeout(resolve, 20));
+      expect(limiter.running).toBe(1);
+
+      const health = await sendVsockRequest('127.0.0.1', listening.port, { type: 'health', requestId: 'h' }, 5000);
+      expect(health).toEqual({ type: 'health', requestId: 'h' });
+      expect(limiter.running).toBe(1);
+
+      proveGate.resolve();
+      expect(await prove).toEqual({ type: 'prove', requestId: 'slow' });
+    });
+  });
+});
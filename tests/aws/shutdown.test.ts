@@ -0,0 +1,45 @@
+import { describe, it, expect, afterEach } from 'vitest';
+import type { Server } from 'node:net';
+import { ShutdownController } from '../../src/shutdown.js';
+import { sendVsockRequest } from '../../src/aws/vsockClient.js';
+import { deferred, testContext, listen } from './fixtures/connection.js';
+
+describe('enclave shutdown', () => {
+  describe('with the connection handler', () => {
+    let server: Server | undefined;
+
+    afterEach(() => {
+      server?.close();
+      server = undefined;
+    });
+
+    it('should complete a request started before shutdown', async () => {
+      const controller = new ShutdownController();
+      const proveGate = deferred();
+      const started = deferred();
+
+      const ctx = testContext((request) => controller.track(async () => {
+        if (request.type === 'prove') {
+          started.resolve();
+          await proveGate.promise;
+        }
+        return { type: request.type, requestId: request.requestId || '' };
+      }));
+      const listening = await listen(ctx);
+      server = listening.server;
+      const { port } = listening;
+
+      const prove = sendVsockRequest('127.0.0.1', port, { type: 'prove', requestId: 'before' }, 5000);
+      await started.promise;
+
+      // What the signal handler does: stop accepting, then drain
+      server!.close();
+      const drained = controller.drain(5000);
+      await expect(sendVsockRequest('127.0.0.1', port, { type: 'health', requestId: 'after' }, 1000)).rejects.toThrow();
+
+      proveGate.resolve();
+      expect(await prove).toEqual({ type: 'prove', requestId: 'before' });
+      await expect(drained).resolves.toBe(true);
+    });
+  });
+});
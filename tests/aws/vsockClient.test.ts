@@ -0,0 +1,203 @@
+import { describe, it, expect, afterEach } from 'vitest';
+import { createServer, type Server, type AddressInfo } from 'node:net';
+import {
+  buildVsockRequest,
+  parseVsockRequestFile,
+  sendVsockRequest,
+  sendVsockRequests,
+  exitCodeForResponse,
+  VSOCK_REQUEST_TYPES,
+} from '../../src/aws/vsockClient.js';
+
+describe('vsockClient', () => {
+  describe('buildVsockRequest()', () => {
+    it('should build a health request with a generated requestId', () => {
+      const request = buildVsockRequest({ type: 'health' });
+      expect(request.type).toBe('health');
+      expect(request.requestId).toMatch(/^cli-health-\d+$/);
+    });
+
+    it('should build a prove request with circuit, inputs and metadata', () => {
+      const request = buildVsockRequest({
+        type: 'prove',
+        requestId: 'req-1',
+        circuitId: 'coinbase_attestation',
+        inputs: { signal_hash: '0xab' },
+        metadata: { timeoutMs: 60000 },
+      });
+      expect(request).toEqual({
+        type: 'prove',
+        requestId: 'req-1',
+        circuitId: 'coinbase_attestation',
+        inputs: { signal_hash: '0xab' },
+        metadata: { timeoutMs: 60000 },
+      });
+    });
+
+    it('should require circuitId and inputs for prove', () => {
+      expect(() => buildVsockRequest({ type: 'prove', inputs: {} })).toThrow('circuitId');
+      expect(() => buildVsockRequest({ type: 'prove', circuitId: 'coinbase_attestation' })).toThrow('inputs');
+    });
+
+    it('should build a verify request with circuit, proof and public inputs', () => {
+      const request = buildVsockRequest({
+        type: 'verify',
+        requestId: 'req-2',
+        circuitId: 'coinbase_attestation',
+        proof: '0xabcd',
+        publicInputs: ['0x01'],
+      });
+      expect(request).toEqual({
+        type: 'verify',
+        requestId: 'req-2',
+        circuitId: 'coinbase_attestation',
+        proof: '0xabcd',
+        publicInputs: ['0x01'],
+      });
+      expect(buildVsockRequest({ type: 'verify', circuitId: 'c', proof: '0xab' }).publicInputs).toEqual([]);
+    });
+
+    it('should carry disableZk on verify requests only when set', () => {
+      const base = { type: 'verify', circuitId: 'c', proof: '0xab' };
+      expect(buildVsockRequest({ ...base, disableZk: true }).disableZk).toBe(true);
+      expect(buildVsockRequest({ ...base, disableZk: false })).not.toHaveProperty('disableZk');
+    });
+
+    it('should require circuitId and proof for verify', () => {
+      expect(() => buildVsockRequest({ type: 'verify', proof: '0xab' })).toThrow('circuitId');
+      expect(() => buildVsockRequest({ type: 'verify', circuitId: 'coinbase_attestation' })).toThrow('proof');
+    });
+
+    it('should require an errorId for errorDetail', () => {
+      const errorId = '0b6f7c9e-4d2a-4f1b-9a63-2f0c1e8d7a55';
+      expect(buildVsockRequest({ type: 'errorDetail', requestId: 'e', errorId })).toEqual({
+        type: 'errorDetail', requestId: 'e', errorId,
+      });
+      expect(() => buildVsockRequest({ type: 'errorDetail' })).toThrow('errorId');
+    });
+
+    it('should carry proofHash only on attestation requests', () => {
+      expect(buildVsockRequest({ type: 'attestation', proofHash: '0x12' }).proofHash).toBe('0x12');
+      expect(buildVsockRequest({ type: 'health', proofHash: '0x12' }).proofHash).toBeUndefined();
+    });
+
+    it('should carry an optional nonce on attestation requests', () => {
+      expect(buildVsockRequest({ type: 'attestation', requestId: 'a', nonce: '0xab' })).toEqual({
+        type: 'attestation', requestId: 'a', nonce: '0xab',
+      });
+      expect(buildVsockRequest({ type: 'attestation', requestId: 'a' })).toEqual({ type: 'attestation', requestId: 'a' });
+      expect(buildVsockRequest({ type: 'health', nonce: '0xab' }).nonce).toBeUndefined();
+    });
+
+    it('should reject unknown request types', () => {
+      expect(() => buildVsockRequest({ type: 'reboot' })).toThrow(
+        `Unknown request type 'reboot'. Supported: ${VSOCK_REQUEST_TYPES.join(', ')}`,
+      );
+    });
+  });
+
+  describe('parseVsockRequestFile()', () => {
+    const ENCRYPTED = {
+      type: 'prove',
+      requestId: 'enc-1',
+      encryptedPayload: { ephemeralPublicKey: 'aa', iv: 'bb', ciphertext: 'cc', authTag: 'dd', keyId: 'ee' },
+      metadata: { timeoutMs: 60_000 },
+    };
+
+    it('should pass a request through unchanged', () => {
+      expect(parseVsockRequestFile(JSON.stringify(ENCRYPTED))).toEqual([ENCRYPTED]);
+    });
+
+    it('should accept an array of requests for framed mode', () => {
+      const requests = [{ type: 'health', requestId: 'a' }, ENCRYPTED];
+      expect(parseVsockRequestFile(JSON.stringify(requests))).toEqual(requests);
+    });
+
+    it('should reject documents that are not requests', () => {
+      expect(() => parseVsockRequestFile('[]')).toThrow('no requests');
+      expect(() => parseVsockRequestFile('"health"')).toThrow('Request 0 in file must be an object');
+      expect(() => parseVsockRequestFile('[{"type":"health"},{"requestId":"b"}]')).toThrow('Request 1');
+      expect(() => parseVsockRequestFile('{')).toThrow();
+    });
+  });
+
+  describe('sendVsockRequest()', () => {
+    let server: Server | undefined;
+
+    afterEach(() => {
+      server?.close();
+      server = undefined;
+    });
+
+    function startEchoServer(): Promise<number> {
+      // Mirrors the enclave framing: read until EOF, answer, close
+      server = createServer({ allowHalfOpen: true }, (socket) => {
+        const chunks: Buffer[] = [];
+        socket.on('data', (chunk: Buffer) => chunks.push(chunk));
+        socket.on('end', () => {
+          const request = JSON.parse(Buffer.concat(chunks).toString('utf-8'));
+          socket.end(JSON.stringify({ type: request.type, requestId: request.requestId, echoed: request }));
+        });
+      });
+      return new Promise((resolve) => {
+        server!.listen(0, '127.0.0.1', () => resolve((server!.address() as AddressInfo).port));
+      });
+    }
+
+    it('should send the request and parse the response over a loopback stream', async () => {
+      const port = await startEchoServer();
+      const request = buildVsockRequest({ type: 'health', requestId: 'loop-1' });
+
+      const response = await sendVsockRequest('127.0.0.1', port, request);
+
+      expect(response.type).toBe('health');
+      expect(response.requestId).toBe('loop-1');
+      expect((response as any).echoed).toEqual(request);
+    });
+
+    it('should send several requests over one connection with line framing', async () => {
+      // Answers each line as it arrives, like the enclave in line mode
+      server = createServer({ allowHalfOpen: true }, (socket) => {
+        let buffered = '';
+        socket.on('data', (chunk: Buffer) => {
+          buffered += chunk.toString('utf-8');
+          let newline: number;
+          while ((newline = buffered.indexOf('\n')) !== -1) {
+            const request = JSON.parse(buffered.slice(0, newline));
+            buffered = buffered.slice(newline + 1);
+            socket.write(JSON.stringify({ type: request.type, requestId: request.requestId }) + '\n');
+          }
+        });
+        socket.on('end', () => socket.end());
+      });
+      const port = await new Promise<number>((resolve) => {
+        server!.listen(0, '127.0.0.1', () => resolve((server!.address() as AddressInfo).port));
+      });
+
+      const responses = await sendVsockRequests('127.0.0.1', port, [
+        buildVsockRequest({ type: 'health', requestId: 'f-1' }),
+        buildVsockRequest({ type: 'listCircuits', requestId: 'f-2' }),
+      ]);
+
+      expect(responses).toEqual([
+        { type: 'health', requestId: 'f-1' },
+        { type: 'listCircuits', requestId: 'f-2' },
+      ]);
+    });
+
+    it('should reject when nothing is listening', async () => {
+      const port = await startEchoServer();
+      server!.close();
+      await expect(
+        sendVsockRequest('127.0.0.1', port, buildVsockRequest({ type: 'health' })),
+      ).rejects.toThrow();
+    });
+  });
+
+  describe('exitCodeForResponse()', () => {
+    it('should map error responses to exit code 1', () => {
+      expect(exitCodeForResponse({ type: 'health', requestId: 'a' })).toBe(0);
+      expect(exitCodeForResponse({ type: 'error', requestId: 'a', error: 'boom' })).toBe(1);
+    });
+  });
+});
@@ -0,0 +1,176 @@
+import { describe, it, expect, beforeEach, afterEach } from 'vitest';
+import * as fs from 'node:fs/promises';
+import * as path from 'node:path';
+import * as os from 'node:os';
+import * as crypto from 'node:crypto';
+import {
+  getStateDir,
+  defaultWorkDirBase,
+  getWorkDirBase,
+  checkWritable,
+  checkWorkDirBase,
+  prepareWorkDirBase,
+  createWorkDir,
+} from '../../src/aws/workDir.js';
+import { runBbProve } from '../../src/aws/bbProve.js';
+import { fakeBb } from './fixtures/fakeBb.js';
+
+describe('workDir', () => {
+  let testDir: string;
+
+  beforeEach(async () => {
+    testDir = path.join(os.tmpdir(), `workdir-test-${crypto.randomUUID()}`);
+    await fs.mkdir(testDir, { recursive: true });
+  });
+
+  afterEach(async () => {
+    await fs.chmod(testDir, 0o755).catch(() => {});
+    await fs.rm(testDir, { recursive: true, force: true }).catch(() => {});
+  });
+
+  describe('getStateDir()', () => {
+    it('should default to a state directory next to the circuits', () => {
+      expect(getStateDir('/app/circuits', {})).toBe('/app/state');
+      expect(getStateDir('/app/circuits/', { STATE_DIR: ' ' })).toBe('/app/state');
+      expect(defaultWorkDirBase('/app/state')).toBe('/app/state/work');
+    });
+
+    it('should honour STATE_DIR', () => {
+      expect(getStateDir('/app/circuits', { STATE_DIR: '/var/lib/prover' })).toBe('/var/lib/prover');
+    });
+  });
+
+  describe('getWorkDirBase()', () => {
+    it('should default to work under the state directory', () => {
+      const stateDefault = defaultWorkDirBase(getStateDir('/app/circuits', {}));
+      expect(getWorkDirBase(stateDefault, {})).toBe('/app/state/work');
+      expect(getWorkDirBase(stateDefault, { WORK_DIR_BASE: '  ' })).toBe('/app/state/work');
+    });
+
+    it('should honour WORK_DIR_BASE', () => {
+      expect(getWorkDirBase('/app/state/work', { WORK_DIR_BASE: '/scratch' })).toBe('/scratch');
+    });
+  });
+
+  describe('checkWorkDirBase()', () => {
+    it('should accept a writable directory and report free space', async () => {
+      const status = await checkWorkDirBase(testDir);
+      expect(status.path).toBe(testDir);
+      expect(status.freeBytes).toBeGreaterThan(0);
+      expect(await fs.readdir(testDir)).toEqual([]);
+    });
+
+    it('should reject a missing path', async () => {
+      const missing = path.join(testDir, 'missing');
+      await expect(checkWorkDirBase(missing)).rejects.toThrow(`WORK_DIR_BASE ${missing} does not exist`);
+    });
+
+    it('should reject a file', async () => {
+      const file = path.join(testDir, 'file');
+      await fs.writeFile(file, '');
+      await expect(checkWorkDirBase(file)).rejects.toThrow('is not a directory');
+    });
+
+    // root ignores directory permissions, so the read-only case only holds for other users
+    it.skipIf(process.getuid?.() === 0)('should reject a read-only directory', async () => {
+      await fs.chmod(testDir, 0o555);
+      await expect(checkWorkDirBase(testDir)).rejects.toThrow('is not writable');
+    });
+  });
+
+  describe('prepareWorkDirBase()', () => {
+    it('should create the default base', async () => {
+      const base = path.join(testDir, 'state', 'work');
+      const status = await prepareWorkDirBase(base, {});
+      expect(status.path).toBe(base);
+      expect(await fs.readdir(base)).toEqual([]);
+    });
+
+    it('should not create a configured WORK_DIR_BASE', async () => {
+      const base = path.join(testDir, 'scratch');
+      await expect(prepareWorkDirBase(base, { WORK_DIR_BASE: base })).rejects.toThrow('does not exist');
+    });
+  });
+
+  describe('createWorkDir()', () => {
+    it('should create a per-request directory under the base', async () => {
+      const first = await createWorkDir(testDir, 'req-1');
+      const second = await createWorkDir(testDir, 'req-1');
+      expect(path.dirname(first)).toBe(testDir);
+      expect(path.basename(first)).toMatch(/^proof-req-1-/);
+      expect(first).not.toBe(second);
+    });
+  });
+
+  describe('read-only circuits directory', () => {
+    let circuitsDir: string;
+    let stateDir: string;
+
+    beforeEach(async () => {
+      circuitsDir = path.join(testDir, 'circuits');
+      stateDir = getStateDir(circuitsDir, {});
+      const target = path.join(circuitsDir, 'coinbase-attestation', 'target');
+      await fs.mkdir(target, { recursive: true });
+      await fs.writeFile(path.join(target, 'coinbase_attestation.json'), '{"bytecode":"H4sI"}');
+      await fs.writeFile(path.join(target, 'vk'), Buffer.alloc(16));
+    });
+
+    afterEach(async () => {
+      await fs.chmod(circuitsDir, 0o755).catch(() => {});
+    });
+
+    async function listTree(dir: string): Promise<string[]> {
+      const entries = await fs.readdir(dir, { recursive: true });
+      return entries.map(String).sort();
+    }
+
+    it('should put every work file under the state directory', async () => {
+      const before = await listTree(circuitsDir);
+      await fs.chmod(circuitsDir, 0o555);
+      const target = path.join(circuitsDir, 'coinbase-attestation', 'target');
+
+      const base = getWorkDirBase(defaultWorkDirBase(stateDir), {});
+      await prepareWorkDirBase(base, {});
+      const workDirs: string[] = [];
+      const result = await runBbProve({
+        requestId: 'req-1',
+        bytecode: path.join(target, 'coinbase_attestation.json'),
+        vk: path.join(target, 'vk'),
+        witness: Buffer.from('w'),
+        settings: { lowMemory: false, disableZk: false },
+        timeoutMs: 10_000,
+        verify: false,
+        checkArtifacts: () => null,
+      }, {
+        workBase: base,
+        runBb: (label, args, options) => {
+          workDirs.push(options.workDir!);
+          return fakeBb(label, args, options);
+        },
+        stallWindowMs: 0,
+        verifyTimeoutMs: 10_000,
+        keepFailedWorkDir: false,
+        log: () => {},
+      });
+
+      expect(result.proof).toHaveLength(64);
+      expect(result.publicInputs).toHaveLength(32);
+      expect(workDirs).toHaveLength(1);
+      expect(workDirs[0].startsWith(stateDir + path.sep)).toBe(true);
+      expect(await fs.readdir(base)).toEqual([]);
+      expect(await listTree(circuitsDir)).toEqual(before);
+    });
+
+    it('should check the circuits directory without creating anything', async () => {
+      const before = await listTree(circuitsDir);
+      expect(await checkWritable(circuitsDir)).toBeNull();
+      expect(await listTree(circuitsDir)).toEqual(before);
+    });
+
+    // root ignores directory permissions, so the read-only case only holds for other users
+    it.skipIf(process.getuid?.() === 0)('should detect the read-only circuits directory', async () => {
+      await fs.chmod(circuitsDir, 0o555);
+      expect(await checkWritable(circuitsDir)).toBe('EACCES');
+    });
+  });
+});
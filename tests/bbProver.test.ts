@@ -18,6 +18,7 @@ vi.mock('node:fs/promises', () => ({
   mkdir: vi.fn(),
   writeFile: vi.fn(),
   readFile: vi.fn(),
+  stat: vi.fn(),
   rm: vi.fn(),
 }));
 
@@ -74,6 +75,7 @@ describe('BbProver', () => {
     vi.mocked(fs.mkdir).mockResolvedValue(undefined as any);
     vi.mocked(fs.writeFile).mockResolvedValue();
     vi.mocked(fs.rm).mockResolvedValue();
+    vi.mocked(fs.stat).mockResolvedValue({ size: 1024 } as any);
     vi.mocked(fs.readFile).mockImplementation(async (filePath: any) => {
       const p = filePath.toString();
       if (p.endsWith('/proof')) return Buffer.from([0xaa, 0xbb, 0xcc, 0xdd]);
@@ -125,6 +127,23 @@ describe('BbProver', () => {
       expect(fs.readFile).toHaveBeenCalledWith(expectedPath, 'utf-8');
     });
 
+    it('rejects an oversized circuit JSON without reading it', async () => {
+      vi.mocked(fs.stat).mockResolvedValue({ size: 65 * 1024 * 1024 } as any);
+
+      await expect(prover.prove('coinbase_attestation', mockCircuitParams)).rejects.toThrow(
+        'Circuit artifact too large',
+      );
+      expect(fs.readFile).not.toHaveBeenCalled();
+    });
+
+    it('rejects a circuit JSON without bytecode', async () => {
+      vi.mocked(fs.readFile).mockResolvedValue(JSON.stringify({ abi: {} }) as any);
+
+      await expect(prover.prove('coinbase_attestation', mockCircuitParams)).rejects.toThrow(
+        'has no bytecode field',
+      );
+    });
+
     it('formats coinbase inputs via inputFormatter', async () => {
       await prover.prove('coinbase_attestation', mockCircuitParams);
 
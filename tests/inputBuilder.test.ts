@@ -153,6 +153,14 @@ describe('hexToBytes', () => {
     expect(bytes.length).toBe(20);
     expect(bytes[0]).toBe(0x95);
   });
+
+  it('should throw on odd-length hex', () => {
+    expect(() => hexToBytes('0xabc')).toThrow('Invalid hex string (3 characters)');
+  });
+
+  it('should throw on non-hex characters', () => {
+    expect(() => hexToBytes('0xzz')).toThrow('Invalid hex string (2 characters)');
+  });
 });
 
 // ─── padBytes ────────────────────────────────────────────────────────────
@@ -0,0 +1,152 @@
+/**
+ * POST /api/v1/prove with a TEE that fails retryably: the client gets 503 with
+ * Retry-After, and the payment nonce it already spent is accepted on retry.
+ */
+
+import { describe, it, expect, beforeEach, vi } from 'vitest';
+import express from 'express';
+import request from 'supertest';
+import { createProofRoutes } from '../src/proof/proofRoutes.js';
+import type { Config } from '../src/config/index.js';
+import type { RedisClient } from '../src/redis/client.js';
+import type { TeeProvider, VsockResponse } from '../src/tee/types.js';
+
+/** The subset of ioredis the routes use for nonces */
+function memoryRedis() {
+  const store = new Map<string, string>();
+  return {
+    store,
+    set: vi.fn(async (key: string, value: string) => { store.set(key, value); return 'OK'; }),
+    getdel: vi.fn(async (key: string) => {
+      const value = store.get(key) ?? null;
+      store.delete(key);
+      return value;
+    }),
+  };
+}
+
+const CONFIG = {
+  paymentMode: 'disabled',
+  paymentPayTo: '',
+  paymentProofPrice: '$0.10',
+  chainRpcUrl: 'https://sepolia.base.org',
+  ethereumRpcUrl: '',
+  a2aBaseUrl: 'http://localhost:4002',
+  x402FacilitatorUrl: '',
+  teeMode: 'nitro',
+  bbPath: 'bb',
+  circuitsDir: './circuits',
+} as Config;
+
+const ENVELOPE = {
+  ephemeralPublicKey: '11'.repeat(32),
+  iv: '22'.repeat(12),
+  ciphertext: '33'.repeat(16),
+  authTag: '44'.repeat(16),
+  keyId: 'key-1',
+};
+
+const TIMEOUT: VsockResponse = {
+  type: 'error',
+  requestId: 'r1',
+  error: 'Proof generation timed out',
+  code: 'PROVE_TIMEOUT',
+  retryable: true,
+};
+
+const PROOF: VsockResponse = {
+  type: 'proof',
+  requestId: 'r1',
+  proof: '0x' + 'ab'.repeat(32),
+  publicInputs: ['0x' + 'cd'.repeat(32)],
+};
+
+describe('POST /api/v1/prove retryable TEE errors', () => {
+  let redis: ReturnType<typeof memoryRedis>;
+  let tee: { [K in keyof TeeProvider]: any };
+  let app: express.Express;
+
+  beforeEach(() => {
+    redis = memoryRedis();
+    tee = {
+      mode: 'nitro',
+      prove: vi.fn(),
+      proveEncrypted: vi.fn(),
+      healthCheck: vi.fn(async () => true),
+      getAttestation: vi.fn(async () => null),
+      generateAttestation: vi.fn(async () => null),
+      getTeePublicKey: vi.fn(async () => null),
+    };
+    app = express();
+    app.use(express.json());
+    app.use('/api/v1', createProofRoutes({
+      redis: redis as unknown as RedisClient,
+      config: CONFIG,
+      teeProvider: tee as TeeProvider,
+    }));
+  });
+
+  async function challenge(): Promise<string> {
+    const response = await request(app).post('/api/v1/prove').send({ circuit: 'coinbase_kyc' });
+    expect(response.status).toBe(402);
+    return response.body.nonce;
+  }
+
+  function submit(nonce: string, body: Record<string, unknown> = { encrypted_payload: ENVELOPE }) {
+    return request(app)
+      .post('/api/v1/prove')
+      .set('X-Payment-Nonce', nonce)
+      .send({ circuit: 'coinbase_kyc', ...body });
+  }
+
+  it('should answer 503 with Retry-After and accept the same nonce on retry', async () => {
+    tee.proveEncrypted.mockResolvedValueOnce(TIMEOUT).mockResolvedValueOnce(PROOF);
+    const nonce = await challenge();
+
+    const first = await submit(nonce);
+    expect(first.status).toBe(503);
+    expect(first.headers['retry-after']).toBe('5');
+    expect(first.body).toEqual({ error: 'PROVE_TIMEOUT', message: 'Proof generation timed out', retryable: true });
+    expect(redis.store.get(`x402:nonce:${nonce}`)).toBe('coinbase_attestation');
+
+    const retry = await submit(nonce);
+    expect(retry.status).toBe(200);
+    expect(retry.body.proof).toBe(PROOF.proof);
+    expect(redis.store.has(`x402:nonce:${nonce}`)).toBe(false);
+  });
+
+  it('should keep the nonce consumed after a permanent TEE error', async () => {
+    tee.proveEncrypted.mockResolvedValueOnce({
+      type: 'error', requestId: 'r1', error: 'Witness unsatisfied', code: 'WITNESS_UNSATISFIED', retryable: false,
+    });
+    const nonce = await challenge();
+
+    const first = await submit(nonce);
+    expect(first.status).toBe(500);
+    expect(first.headers['retry-after']).toBeUndefined();
+
+    const retry = await submit(nonce);
+    expect(retry.status).toBe(400);
+    expect(retry.body.error).toBe('INVALID_NONCE');
+  });
+
+  it('should map KEY_ROTATED by code and by the old message to 409', async () => {
+    tee.proveEncrypted
+      .mockResolvedValueOnce({ type: 'error', requestId: 'r1', error: 'rotated', code: 'KEY_ROTATED', retryable: false })
+      .mockResolvedValueOnce({ type: 'error', requestId: 'r2', error: 'Key ID mismatch: expected a, got b' });
+
+    expect((await submit(await challenge())).status).toBe(409);
+    expect((await submit(await challenge())).status).toBe(409);
+  });
+
+  it('should reject plaintext inputs in nitro mode before the TEE prove path', async () => {
+    // Nitro mode rejects plaintext inputs here, so the plaintext TEE branch of
+    // generateProofFromInputs is never reached from POST /prove
+    const nonce = await challenge();
+
+    const response = await submit(nonce, { inputs: { signal_hash: '0x00' } });
+    expect(response.status).toBe(400);
+    expect(response.body.error).toBe('PLAINTEXT_REJECTED');
+    expect(tee.prove).not.toHaveBeenCalled();
+  });
+});
@@ -0,0 +1,125 @@
+import { describe, it, expect, afterEach } from 'vitest';
+import type { Server } from 'node:http';
+import type { AddressInfo } from 'node:net';
+import express from 'express';
+import request from 'supertest';
+import {
+  ShutdownController,
+  getShutdownGraceMs,
+  shutdownHttpServer,
+  trackHttpRequests,
+  DEFAULT_SHUTDOWN_GRACE_MS,
+} from '../src/shutdown.js';
+import { deferred } from './aws/fixtures/connection.js';
+
+describe('shutdown', () => {
+  describe('getShutdownGraceMs()', () => {
+    it('should default to 120 seconds', () => {
+      expect(getShutdownGraceMs({})).toBe(DEFAULT_SHUTDOWN_GRACE_MS);
+      expect(getShutdownGraceMs({ SHUTDOWN_GRACE_SECS: 'soon' })).toBe(DEFAULT_SHUTDOWN_GRACE_MS);
+      expect(getShutdownGraceMs({ SHUTDOWN_GRACE_SECS: '-1' })).toBe(DEFAULT_SHUTDOWN_GRACE_MS);
+    });
+
+    it('should honour SHUTDOWN_GRACE_SECS', () => {
+      expect(getShutdownGraceMs({ SHUTDOWN_GRACE_SECS: '30' })).toBe(30_000);
+      expect(getShutdownGraceMs({ SHUTDOWN_GRACE_SECS: '0' })).toBe(0);
+    });
+  });
+
+  describe('ShutdownController', () => {
+    it('should drain immediately when idle', async () => {
+      const controller = new ShutdownController();
+      expect(controller.draining).toBe(false);
+      await expect(controller.drain(1000)).resolves.toBe(true);
+      expect(controller.draining).toBe(true);
+    });
+
+    it('should wait for in-flight requests', async () => {
+      const controller = new ShutdownController();
+      const gate = deferred();
+      const task = controller.track(() => gate.promise.then(() => 'done'));
+      expect(controller.activeRequests).toBe(1);
+
+      const drained = controller.drain(5000);
+      gate.resolve();
+      await expect(task).resolves.toBe('done');
+      await expect(drained).resolves.toBe(true);
+      expect(controller.activeRequests).toBe(0);
+    });
+
+    it('should give up after the grace period', async () => {
+      const controller = new ShutdownController();
+      const gate = deferred();
+      const task = controller.track(() => gate.promise);
+
+      await expect(controller.drain(20)).resolves.toBe(false);
+      expect(controller.activeRequests).toBe(1);
+      gate.resolve();
+      await task;
+    });
+
+    it('should count failed requests as finished', async () => {
+      const controller = new ShutdownController();
+      await expect(controller.track(async () => { throw new Error('boom'); })).rejects.toThrow('boom');
+      expect(controller.activeRequests).toBe(0);
+    });
+  });
+
+  describe('HTTP server', () => {
+    let server: Server | undefined;
+
+    afterEach(() => {
+      server?.closeAllConnections();
+      server?.close();
+      server = undefined;
+    });
+
+    function slowApp(controller: ShutdownController, gate: Promise<void>, started: () => void) {
+      const app = express();
+      app.use(trackHttpRequests(controller));
+      app.get('/health', (_req, res) => res.json({ status: 'healthy' }));
+      app.post('/prove', async (_req, res) => {
+        started();
+        await gate;
+        res.json({ proof: '0xabcd' });
+      });
+      return app;
+    }
+
+    it('should answer 503 to every request once draining', async () => {
+      const controller = new ShutdownController();
+      const app = slowApp(controller, Promise.resolve(), () => {});
+      expect((await request(app).get('/health')).status).toBe(200);
+
+      await controller.drain(0);
+      const health = await request(app).get('/health');
+      expect(health.status).toBe(503);
+      expect(health.body).toEqual({ status: 'draining', error: 'Server is shutting down' });
+      expect((await request(app).post('/prove')).status).toBe(503);
+    });
+
+    it('should complete a request started before shutdown', async () => {
+      const controller = new ShutdownController();
+      const proveGate = deferred();
+      const started = deferred();
+      const app = slowApp(controller, proveGate.promise, started.resolve);
+      const port = await new Promise<number>((resolve) => {
+        server = app.listen(0, '127.0.0.1', () => resolve((server!.address() as AddressInfo).port));
+      });
+
+      const prove = fetch(`http://127.0.0.1:${port}/prove`, { method: 'POST' });
+      await started.promise;
+      expect(controller.activeRequests).toBe(1);
+
+      // The test-only hook: what the SIGTERM handler in src/index.ts runs
+      const drained = shutdownHttpServer(server!, controller, 5000);
+      await expect(fetch(`http://127.0.0.1:${port}/health`)).rejects.toThrow();
+
+      proveGate.resolve();
+      const response = await prove;
+      expect(response.status).toBe(200);
+      expect(await response.json()).toEqual({ proof: '0xabcd' });
+      await expect(drained).resolves.toBe(true);
+    });
+  });
+});
@@ -247,6 +247,38 @@ describe('EnclaveClient', () => {
       expect(isHealthy).toBe(true);
     });
 
+    it('should return false while the enclave is draining', async () => {
+      const config: TeeConfig = {
+        mode: 'nitro',
+        enclaveCid: 16,
+        enclavePort: 5000,
+        attestationEnabled: false,
+      };
+      const client = new EnclaveClient(config);
+
+      const handlers: Record<string, Function> = {};
+      mockSocket.on.mockImplementation((event: string, handler: Function) => {
+        handlers[event] = handler;
+        if (event === 'connect') {
+          setTimeout(() => handler(), 0);
+        } else if (event === 'data') {
+          setTimeout(() => {
+            const response: VsockResponse = {
+              type: 'health',
+              requestId: 'health-123',
+              status: 'draining',
+            };
+            handler(Buffer.from(JSON.stringify(response)));
+            if (handlers['end']) setTimeout(() => handlers['end'](), 5);
+          }, 10);
+        }
+        return mockSocket;
+      });
+
+      const isHealthy = await client.healthCheck();
+      expect(isHealthy).toBe(false);
+    });
+
     it('should return false on error', async () => {
       const config: TeeConfig = {
         mode: 'nitro',